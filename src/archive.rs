@@ -0,0 +1,385 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+use colored::Colorize;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::{
+    chc_data_dir, confirm_deletion, conversation_backups, conversation_files, format_size, get_display_title, get_short_workspace, history,
+    path_size, Conversation, EXIT_NOTHING_TO_DO, EXIT_PARTIAL_FAILURE,
+};
+
+/// Stored first in every bundle, next to `projects/`
+const MANIFEST_FILE: &str = "chc-archive.json";
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Compression {
+    /// Zstandard (`.tar.zst`)
+    Zst,
+    /// Gzip (`.tar.gz`)
+    Gz,
+}
+
+impl Compression {
+    fn extension(self) -> &'static str {
+        match self {
+            Compression::Zst => "tar.zst",
+            Compression::Gz => "tar.gz",
+        }
+    }
+}
+
+pub struct ArchiveOptions {
+    pub compression: Compression,
+    /// One bundle per workspace instead of one per run
+    pub per_workspace: bool,
+    /// Folder the bundles are written to, the chc data dir's `archive/` by default
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedConversation {
+    session_id: String,
+    workspace_path: String,
+    title: String,
+    /// Paths inside the bundle, relative to the Claude data directory
+    items: Vec<PathBuf>,
+    /// Lines removed from `history.jsonl`, put back on unarchive
+    #[serde(default)]
+    history: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleManifest {
+    created_at: DateTime<Utc>,
+    /// `.claude` folder the conversations were archived from
+    claude_dir: PathBuf,
+    conversations: Vec<ArchivedConversation>,
+}
+
+/// Conversations that go into the same bundle, with the files each one owns
+struct Bundle<'a> {
+    path: PathBuf,
+    claude_dir: PathBuf,
+    conversations: Vec<(&'a Conversation, Vec<PathBuf>)>,
+}
+
+/// `base.ext`, or `base-2.ext`, `base-3.ext`... if that is taken
+fn unique_path(dir: &Path, base: &str, ext: &str, taken: &[PathBuf]) -> PathBuf {
+    let mut path = dir.join(format!("{}.{}", base, ext));
+    let mut n = 2;
+    while path.exists() || taken.contains(&path) {
+        path = dir.join(format!("{}-{}.{}", base, n, ext));
+        n += 1;
+    }
+    path
+}
+
+/// Group conversations by Claude data directory, and by workspace with `per_workspace`
+fn plan<'a>(conversations: &[&'a Conversation], opts: &ArchiveOptions, output: &Path) -> Result<Vec<Bundle<'a>>> {
+    let mut groups: BTreeMap<(PathBuf, Option<PathBuf>), Vec<&Conversation>> = BTreeMap::new();
+    for &conv in conversations {
+        let workspace = opts.per_workspace.then(|| conv.workspace_folder.clone());
+        groups.entry((conv.root.clone(), workspace)).or_default().push(conv);
+    }
+
+    let stamp = Local::now().format("%Y%m%dT%H%M%S");
+    let mut bundles: Vec<Bundle> = Vec::new();
+    for ((claude_dir, workspace), convs) in groups {
+        let base = match workspace.as_deref().and_then(|w| w.file_name()).and_then(|n| n.to_str()) {
+            Some(name) => format!("{}-{}", name.trim_start_matches('-'), stamp),
+            None => format!("chc-{}", stamp),
+        };
+        let taken: Vec<PathBuf> = bundles.iter().map(|b| b.path.clone()).collect();
+        let path = unique_path(output, &base, opts.compression.extension(), &taken);
+
+        let conversations = convs.into_iter()
+            .map(|conv| Ok((conv, conversation_files(conv)?)))
+            .collect::<Result<Vec<_>>>()?;
+        bundles.push(Bundle { path, claude_dir, conversations });
+    }
+    Ok(bundles)
+}
+
+/// Tar the manifest and every file under its path relative to the Claude data directory
+fn append_all<W: Write>(writer: W, manifest: &BundleManifest, files: &[PathBuf], claude_dir: &Path) -> Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+
+    let json = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created_at.timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_FILE, json.as_slice())?;
+
+    for path in files {
+        let name = path.strip_prefix(claude_dir)
+            .with_context(|| format!("{} is outside {}", path.display(), claude_dir.display()))?;
+        if path.is_dir() {
+            builder.append_dir_all(name, path)?;
+        } else {
+            builder.append_path_with_name(path, name)?;
+        }
+    }
+
+    Ok(builder.into_inner()?)
+}
+
+/// Write the bundle through a `.partial` file, so a bundle that exists is always complete
+fn write_bundle(bundle: &Bundle, manifest: &BundleManifest, compression: Compression) -> Result<()> {
+    let files: Vec<PathBuf> = bundle.conversations.iter().flat_map(|(_, files)| files.iter().cloned()).collect();
+    let mut partial = bundle.path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let file = fs::File::create(&partial).with_context(|| format!("Failed to create {}", partial.display()))?;
+    let written = match compression {
+        Compression::Gz => append_all(GzEncoder::new(file, flate2::Compression::default()), manifest, &files, &bundle.claude_dir)
+            .and_then(|enc| Ok(enc.finish()?)),
+        Compression::Zst => append_all(zstd::Encoder::new(file, 0)?, manifest, &files, &bundle.claude_dir)
+            .and_then(|enc| Ok(enc.finish()?)),
+    };
+
+    let result = written
+        .and_then(|file| Ok(file.sync_all()?))
+        .and_then(|_| fs::rename(&partial, &bundle.path).with_context(|| format!("Failed to write {}", bundle.path.display())));
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+fn remove_path(path: &Path) -> Result<()> {
+    let removed = if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+    removed.with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+/// Pack conversations into compressed bundles, then remove the originals and their `history.jsonl` entries
+pub fn run(conversations: &[&Conversation], opts: &ArchiveOptions, dry_run: bool, yes: bool) -> Result<ExitCode> {
+    if conversations.is_empty() {
+        println!("{}", "No matching conversations found.".yellow());
+        return Ok(ExitCode::from(EXIT_NOTHING_TO_DO));
+    }
+
+    let output = match opts.output {
+        Some(ref dir) => dir.clone(),
+        None => chc_data_dir()?.join("archive"),
+    };
+    let bundles = plan(conversations, opts, &output)?;
+
+    let total_size: u64 = conversations.iter().map(|c| c.total_size).sum();
+    println!("Found {} conversations to archive ({}):", conversations.len().to_string().yellow(), format_size(total_size));
+    for bundle in &bundles {
+        println!();
+        println!("  {} {}", "->".green(), bundle.path.display());
+        for (conv, files) in &bundle.conversations {
+            println!("     - {} {} ({}, {})",
+                get_display_title(conv),
+                conv.session_id.dimmed(),
+                get_short_workspace(&conv.workspace_path),
+                format_size(conv.total_size)
+            );
+            if dry_run {
+                for path in files {
+                    let kind = if path.is_dir() { "dir " } else { "file" };
+                    println!("         {} {} ({})", kind.dimmed(), path.display(), format_size(path_size(path)));
+                }
+            }
+        }
+    }
+    println!();
+
+    if dry_run {
+        println!("{}", "Dry run, nothing was archived.".yellow());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let prompt = format!("Archive {} conversations into {} bundle(s) and remove the originals?", conversations.len(), bundles.len());
+    if !confirm_deletion(prompt, yes)? {
+        println!("{}", "Cancelled.".yellow());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    fs::create_dir_all(&output).with_context(|| format!("Failed to create {}", output.display()))?;
+
+    let mut archived = 0;
+    let mut errors = 0;
+    // Index entries are removed once all bundles are written, one rewrite per `.claude` folder
+    let mut removed_sessions: HashMap<&Path, Vec<String>> = HashMap::new();
+    for bundle in &bundles {
+        let mut manifest = BundleManifest {
+            created_at: Utc::now(),
+            claude_dir: bundle.claude_dir.clone(),
+            conversations: Vec::new(),
+        };
+        for (conv, files) in &bundle.conversations {
+            let history = history::session_lines(&bundle.claude_dir, &conv.session_id).unwrap_or_default();
+            manifest.conversations.push(ArchivedConversation {
+                session_id: conv.session_id.clone(),
+                workspace_path: conv.workspace_path.clone(),
+                title: get_display_title(conv),
+                items: files.iter().filter_map(|p| p.strip_prefix(&bundle.claude_dir).ok()).map(Path::to_path_buf).collect(),
+                history,
+            });
+        }
+
+        // Nothing is removed unless the whole bundle made it to disk
+        if let Err(e) = write_bundle(bundle, &manifest, opts.compression) {
+            eprintln!("  {} {} - {:#}", "ERR".red(), bundle.path.display(), e);
+            errors += bundle.conversations.len();
+            continue;
+        }
+
+        for (conv, files) in &bundle.conversations {
+            // Backups are not part of the bundle, they would keep what was redacted around
+            let backups = conversation_backups(conv).unwrap_or_default();
+            match files.iter().chain(&backups).try_for_each(|p| remove_path(p)) {
+                Ok(()) => {
+                    archived += 1;
+                    removed_sessions.entry(&bundle.claude_dir).or_default().push(conv.session_id.clone());
+                }
+                Err(e) => {
+                    eprintln!("  {} {} - {}", "ERR".red(), conv.session_id, e);
+                    errors += 1;
+                }
+            }
+        }
+
+        let size = fs::metadata(&bundle.path).map(|m| m.len()).unwrap_or(0);
+        println!("  {} {} ({})", "OK".green(), bundle.path.display(), format_size(size));
+    }
+    for (claude_dir, session_ids) in removed_sessions {
+        if let Err(e) = history::remove_sessions(claude_dir, &session_ids) {
+            eprintln!("  {} Could not update history.jsonl: {}", "WARN".yellow(), e);
+        }
+    }
+
+    println!();
+    if errors > 0 {
+        println!("{} Done! Archived {} conversations ({} failed)", "WARN".yellow(), archived, errors);
+        return Ok(ExitCode::from(EXIT_PARTIAL_FAILURE));
+    }
+    println!("{}", format!("Done! Archived {} conversations.", archived).green().bold());
+    println!("{}", "Restore with: chc unarchive <bundle>".dimmed());
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Open a bundle, telling gzip from zstd by its magic bytes rather than its name
+fn open(path: &Path) -> Result<tar::Archive<Box<dyn Read>>> {
+    let mut file = fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic).with_context(|| format!("{} is not an archive bundle", path.display()))?;
+    file.seek(SeekFrom::Start(0))?;
+
+    let reader: Box<dyn Read> = match magic {
+        [0x1f, 0x8b, _, _] => Box::new(GzDecoder::new(BufReader::new(file))),
+        [0x28, 0xb5, 0x2f, 0xfd] => Box::new(zstd::Decoder::new(file)?),
+        _ => anyhow::bail!("{} is neither a .tar.gz nor a .tar.zst bundle", path.display()),
+    };
+    Ok(tar::Archive::new(reader))
+}
+
+fn read_manifest(path: &Path) -> Result<BundleManifest> {
+    let mut archive = open(path)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.as_ref() == Path::new(MANIFEST_FILE) {
+            let mut json = String::new();
+            entry.read_to_string(&mut json)?;
+            return serde_json::from_str(&json).with_context(|| format!("Invalid {} in {}", MANIFEST_FILE, path.display()));
+        }
+    }
+    anyhow::bail!("{} has no {}, it was not created by chc archive", path.display(), MANIFEST_FILE)
+}
+
+/// Extract a bundle back into its Claude data directory (or `claude_dir`) and restore its `history.jsonl` entries
+pub fn unarchive(bundle: &Path, claude_dir: Option<&Path>, dry_run: bool) -> Result<()> {
+    let manifest = read_manifest(bundle)?;
+    let target = claude_dir.unwrap_or(&manifest.claude_dir);
+
+    // Refuse to overwrite anything that was recreated since
+    for conv in &manifest.conversations {
+        for item in &conv.items {
+            let path = target.join(item);
+            if path.exists() {
+                anyhow::bail!("Cannot unarchive, {} already exists", path.display());
+            }
+        }
+    }
+
+    if dry_run {
+        println!("Would restore {} conversations into {}:", manifest.conversations.len(), target.display());
+        for conv in &manifest.conversations {
+            println!("  - {} ({}, {} files)", conv.title, conv.workspace_path.dimmed(), conv.items.len());
+        }
+        println!();
+        println!("{}", "Dry run, nothing was restored.".yellow());
+        return Ok(());
+    }
+
+    let mut archive = open(bundle)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if path == Path::new(MANIFEST_FILE) { continue; }
+        if !entry.unpack_in(target).with_context(|| format!("Failed to extract {}", path.display()))? {
+            anyhow::bail!("Refusing to extract {} outside {}", path.display(), target.display());
+        }
+    }
+
+    for conv in &manifest.conversations {
+        println!("  {} {} ({})", "OK".green(), conv.title, conv.workspace_path.dimmed());
+    }
+    let history: Vec<String> = manifest.conversations.iter().flat_map(|c| c.history.iter().cloned()).collect();
+    if let Err(e) = history::restore_lines(target, &history) {
+        eprintln!("  {} Could not restore history.jsonl entries: {}", "WARN".yellow(), e);
+    }
+
+    println!();
+    println!("{} Restored {} conversations into {}", "OK".green().bold(), manifest.conversations.len(), target.display());
+    println!("{}", format!("{} was kept, delete it once you no longer need it", bundle.display()).dimmed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{conversation, history_line, temp_claude_dir, write_transcript};
+
+    #[test]
+    fn archived_conversation_comes_back_byte_for_byte() {
+        let dir = temp_claude_dir("archive-round-trip");
+        let session = "archive-round-trip-1";
+        let transcript = write_transcript(&dir, "-tmp-ws", session, &["first", "second"]);
+        let agent = transcript.with_extension("").join("subagents").join("agent-a1.jsonl");
+        fs::create_dir_all(agent.parent().unwrap()).unwrap();
+        fs::write(&agent, b"{\"type\":\"user\",\"message\":{\"content\":\"caf\xe9\"}}\n").unwrap();
+        let files = [(transcript.clone(), fs::read(&transcript).unwrap()), (agent.clone(), fs::read(&agent).unwrap())];
+        let history = [history_line(session, 1), history_line("other", 2), history_line(session, 3)].join("\n") + "\n";
+        fs::write(dir.join("history.jsonl"), &history).unwrap();
+
+        let opts = ArchiveOptions { compression: Compression::Zst, per_workspace: false, output: Some(dir.join("out")) };
+        let code = run(&[&conversation(&dir, session)], &opts, false, true).unwrap();
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(!transcript.exists() && !transcript.with_extension("").exists());
+        assert_eq!(fs::read_to_string(dir.join("history.jsonl")).unwrap(), history_line("other", 2) + "\n");
+
+        let bundle = fs::read_dir(dir.join("out")).unwrap().next().unwrap().unwrap().path();
+        unarchive(&bundle, None, false).unwrap();
+        for (path, content) in &files {
+            assert_eq!(&fs::read(path).unwrap(), content, "{}", path.display());
+        }
+        assert_eq!(fs::read_to_string(dir.join("history.jsonl")).unwrap(), history);
+        assert!(unarchive(&bundle, None, false).is_err(), "the restored files are not overwritten");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,336 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use rayon::prelude::*;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::{
+    backup_file, confirm_deletion, file_stamp, format_size, get_display_title, get_short_workspace, subagent_paths,
+    write_atomic_unchanged, Conversation, EXIT_NOTHING_TO_DO, EXIT_PARTIAL_FAILURE,
+};
+
+/// Keys in `toolUseResult` that hold base64 image data
+const IMAGE_DATA_KEYS: &[&str] = &["base64", "data"];
+
+pub struct CompactOptions {
+    /// Tool results larger than this (in bytes of JSON) are replaced
+    pub max_tool_result: u64,
+    pub strip_images: bool,
+    /// Copy each transcript to the backups folder before rewriting it
+    pub backup: bool,
+}
+
+fn json_len(value: &Value) -> u64 {
+    serde_json::to_string(value).map(|s| s.len() as u64).unwrap_or(0)
+}
+
+/// A text block standing in for an image, `[image/png, 1.2 MB removed by chc compact]`
+fn image_placeholder(image: &Value) -> Value {
+    let media_type = image.pointer("/source/media_type").and_then(|t| t.as_str()).unwrap_or("image");
+    serde_json::json!({
+        "type": "text",
+        "text": format!("[{}, {} removed by chc compact]", media_type, format_size(json_len(image))),
+    })
+}
+
+fn is_image(block: &Value) -> bool {
+    block.get("type").and_then(|t| t.as_str()) == Some("image")
+}
+
+/// Replace image blocks in a content list, returns whether any were
+fn strip_images(blocks: &mut [Value]) -> bool {
+    let mut changed = false;
+    for block in blocks.iter_mut().filter(|b| is_image(b)) {
+        *block = image_placeholder(block);
+        changed = true;
+    }
+    changed
+}
+
+/// Compact one `message.content` list: images, then oversized tool results
+fn compact_content(blocks: &mut [Value], opts: &CompactOptions) -> bool {
+    let mut changed = opts.strip_images && strip_images(blocks);
+
+    for block in blocks.iter_mut() {
+        if block.get("type").and_then(|t| t.as_str()) != Some("tool_result") {
+            continue;
+        }
+        let Some(content) = block.get_mut("content") else { continue };
+        if opts.strip_images {
+            if let Value::Array(items) = content {
+                changed |= strip_images(items);
+            }
+        }
+        let size = json_len(content);
+        if size > opts.max_tool_result {
+            // A string is as valid a tool_result content as a list of blocks
+            *content = Value::String(format!("[tool result of {} removed by chc compact]", format_size(size)));
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// `toolUseResult` repeats the tool output for the UI, its shape depends on the tool.
+/// Only oversized strings (and image data) are replaced so the shape stays the same.
+fn compact_tool_use_result(value: &mut Value, key: Option<&str>, opts: &CompactOptions) -> bool {
+    match value {
+        Value::String(s) => {
+            let size = s.len() as u64;
+            let image = opts.strip_images && key.is_some_and(|k| IMAGE_DATA_KEYS.contains(&k));
+            if size > opts.max_tool_result || (image && size > 0) {
+                *s = format!("[{} removed by chc compact]", format_size(size));
+                return true;
+            }
+            false
+        }
+        Value::Array(items) => items.iter_mut().fold(false, |changed, v| compact_tool_use_result(v, None, opts) | changed),
+        Value::Object(map) => map.iter_mut().fold(false, |changed, (k, v)| compact_tool_use_result(v, Some(k), opts) | changed),
+        _ => false,
+    }
+}
+
+/// The compacted line, or `None` if there is nothing to compact in it
+fn compact_line(line: &str, opts: &CompactOptions) -> Result<Option<String>> {
+    let Ok(mut entry) = serde_json::from_str::<Value>(line) else { return Ok(None) };
+    let mut changed = false;
+    if let Some(Value::Array(blocks)) = entry.pointer_mut("/message/content") {
+        changed |= compact_content(blocks, opts);
+    }
+    if let Some(result) = entry.get_mut("toolUseResult") {
+        changed |= compact_tool_use_result(result, None, opts);
+    }
+    Ok(if changed { Some(serde_json::to_string(&entry)?) } else { None })
+}
+
+/// The compacted transcript, or `None` if nothing in it is over the limits
+fn compact_file(path: &Path, opts: &CompactOptions) -> Result<Option<Vec<u8>>> {
+    let content = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut out = Vec::with_capacity(content.len());
+    let mut changed = false;
+
+    // A line that isn't valid UTF-8 is decoded lossily, but only rewritten if there is something to compact in it
+    for line in content.split_inclusive(|&b| b == b'\n') {
+        let (text, newline) = line.strip_suffix(b"\n").map_or((line, &b""[..]), |t| (t, b"\n"));
+        match compact_line(&String::from_utf8_lossy(text), opts)? {
+            Some(compacted) => {
+                out.extend_from_slice(compacted.as_bytes());
+                out.extend_from_slice(newline);
+                changed = true;
+            }
+            None => out.extend_from_slice(line),
+        }
+    }
+    Ok(changed.then_some(out))
+}
+
+/// Compact a transcript in place, after backing it up if `opts.backup` is set. Returns the bytes saved and the backup.
+fn rewrite(path: &Path, opts: &CompactOptions) -> Result<(u64, Option<PathBuf>)> {
+    let stamp = file_stamp(path);
+    let before = fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?.len();
+    let Some(content) = compact_file(path, opts)? else { return Ok((0, None)) };
+
+    let backup = if opts.backup { backup_file(path)? } else { None };
+    if !write_atomic_unchanged(path, &content, stamp)? {
+        if let Some(backup) = backup {
+            let _ = fs::remove_file(backup);
+        }
+        anyhow::bail!("Changed while it was being compacted, run compact again");
+    }
+    Ok((before.saturating_sub(content.len() as u64), backup))
+}
+
+/// A transcript that gets smaller, the conversation's own or one of its subagents'
+struct Target<'a> {
+    conv: &'a Conversation,
+    path: PathBuf,
+    size: u64,
+    compacted_size: u64,
+}
+
+/// Shrink transcripts by replacing oversized tool results (and images) with placeholders noting their size
+pub fn run(conversations: &[&Conversation], opts: &CompactOptions, dry_run: bool, yes: bool) -> Result<ExitCode> {
+    let mut files: Vec<(&Conversation, PathBuf)> = Vec::new();
+    for &conv in conversations.iter().filter(|c| !c.is_empty) {
+        files.push((conv, conv.path.clone()));
+        files.extend(subagent_paths(conv)?.into_iter().map(|p| (conv, p)));
+    }
+
+    // Measure first, the compacted files are only kept in memory one at a time when writing
+    let mut targets: Vec<Target> = files.into_par_iter()
+        .filter_map(|(conv, path)| {
+            let size = fs::metadata(&path).ok()?.len();
+            let compacted = compact_file(&path, opts).ok()??;
+            Some(Target { conv, path, size, compacted_size: compacted.len() as u64 })
+        })
+        .filter(|t| t.compacted_size < t.size)
+        .collect();
+    targets.sort_by_key(|t| std::cmp::Reverse(t.size - t.compacted_size));
+
+    if targets.is_empty() {
+        println!("{}", "Nothing to compact.".yellow());
+        return Ok(ExitCode::from(EXIT_NOTHING_TO_DO));
+    }
+
+    let total_saved: u64 = targets.iter().map(|t| t.size - t.compacted_size).sum();
+    println!("Found {} transcripts to compact, saving {}:", targets.len().to_string().yellow(), format_size(total_saved).green());
+    println!();
+    for target in &targets {
+        let label = if target.path == target.conv.path {
+            get_display_title(target.conv)
+        } else {
+            format!("{} {}", get_display_title(target.conv), target.path.file_stem().and_then(|n| n.to_str()).unwrap_or("").dimmed())
+        };
+        println!("  - {:>10} -> {:>10}  {} {} ({})",
+            format_size(target.size),
+            format_size(target.compacted_size),
+            format!("-{}", format_size(target.size - target.compacted_size)).green(),
+            label,
+            get_short_workspace(&target.conv.workspace_path)
+        );
+        if dry_run {
+            println!("      {} {}", "file".dimmed(), target.path.display());
+        }
+    }
+    println!();
+
+    if dry_run {
+        println!("{}", "Dry run, nothing was changed.".yellow());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let prompt = if opts.backup {
+        format!("Compact {} transcripts?", targets.len())
+    } else {
+        format!("Compact {} transcripts? The removed content cannot be restored.", targets.len())
+    };
+    if !confirm_deletion(prompt, yes)? {
+        println!("{}", "Cancelled.".yellow());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut saved = 0;
+    let mut errors = 0;
+    for target in &targets {
+        match rewrite(&target.path, opts) {
+            Ok((bytes, backup)) => {
+                saved += bytes;
+                let backup = backup.map(|b| format!(", backup {}", b.display())).unwrap_or_default();
+                println!("  {} {} {}", "OK".green(), target.path.display(), format!("-{}{}", format_size(bytes), backup).dimmed());
+            }
+            Err(e) => {
+                eprintln!("  {} {} - {:#}", "ERR".red(), target.path.display(), e);
+                errors += 1;
+            }
+        }
+    }
+    println!();
+
+    if errors > 0 {
+        println!("{} Done! Saved {} ({} transcripts failed)", "WARN".yellow(), format_size(saved), errors);
+    } else {
+        println!("{}", format!("Done! Compacted {} transcripts, saved {}.", targets.len(), format_size(saved)).green().bold());
+    }
+    if opts.backup {
+        println!("{}", "The space is only freed once the backups are deleted.".yellow());
+    }
+    if errors > 0 {
+        return Ok(ExitCode::from(EXIT_PARTIAL_FAILURE));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn opts(max_tool_result: u64, strip_images: bool) -> CompactOptions {
+        CompactOptions { max_tool_result, strip_images, backup: false }
+    }
+
+    fn image(data: &str) -> Value {
+        json!({"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": data}})
+    }
+
+    fn compact(entry: Value, opts: &CompactOptions) -> Option<Value> {
+        compact_line(&entry.to_string(), opts).unwrap().map(|line| serde_json::from_str(&line).unwrap())
+    }
+
+    #[test]
+    fn small_lines_are_left_alone() {
+        let entry = json!({"message": {"content": [{"type": "tool_result", "tool_use_id": "t1", "content": "ok"}]}});
+        assert_eq!(compact(entry, &opts(1024, true)), None);
+    }
+
+    #[test]
+    fn oversized_tool_results_become_a_placeholder() {
+        let entry = json!({"message": {"content": [
+            {"type": "tool_result", "tool_use_id": "t1", "content": "x".repeat(2000)},
+            {"type": "text", "text": "y".repeat(2000)},
+        ]}});
+        let compacted = compact(entry, &opts(1024, false)).unwrap();
+
+        let blocks = compacted["message"]["content"].as_array().unwrap();
+        assert_eq!(blocks[0]["tool_use_id"], "t1");
+        assert!(blocks[0]["content"].as_str().unwrap().starts_with("[tool result of "));
+        assert_eq!(blocks[1]["text"].as_str().unwrap().len(), 2000, "only tool results are compacted");
+    }
+
+    #[test]
+    fn images_inside_tool_results_are_stripped() {
+        let entry = json!({"message": {"content": [
+            {"type": "tool_result", "tool_use_id": "t1", "content": [{"type": "text", "text": "screenshot"}, image("iVBORw0KGgo")]},
+        ]}});
+        assert_eq!(compact(entry.clone(), &opts(u64::MAX, false)), None);
+
+        let compacted = compact(entry, &opts(u64::MAX, true)).unwrap();
+        let content = &compacted["message"]["content"][0]["content"];
+        assert_eq!(content[0]["text"], "screenshot");
+        assert_eq!(content[1]["type"], "text");
+        assert!(content[1]["text"].as_str().unwrap().starts_with("[image/png, "));
+    }
+
+    #[test]
+    fn tool_use_result_keeps_its_shape() {
+        let entry = json!({"toolUseResult": {
+            "stdout": "x".repeat(2000),
+            "stderr": "",
+            "interrupted": false,
+            "file": {"type": "image", "base64": "iVBORw0KGgo", "size": 11},
+        }});
+        let compacted = compact(entry, &opts(1024, true)).unwrap();
+
+        let result = compacted["toolUseResult"].as_object().unwrap();
+        assert_eq!(result.keys().collect::<Vec<_>>(), vec!["stdout", "stderr", "interrupted", "file"]);
+        assert!(result["stdout"].as_str().unwrap().ends_with("removed by chc compact]"));
+        assert_eq!(result["stderr"], "");
+        assert_eq!(result["interrupted"], false);
+        assert!(result["file"]["base64"].as_str().unwrap().ends_with("removed by chc compact]"));
+        assert_eq!(result["file"]["size"], 11);
+    }
+
+    #[test]
+    fn invalid_utf8_lines_are_compacted_or_kept_byte_for_byte() {
+        let dir = crate::testing::temp_claude_dir("compact-invalid-utf8");
+        let path = dir.join("t.jsonl");
+        let big = json!({"message": {"content": [{"type": "tool_result", "tool_use_id": "t1", "content": "x".repeat(2000)}]}});
+        let mut content = b"{\"message\":{\"content\":\"caf\xe9\"}}\n".to_vec();
+        let kept = content.len();
+        content.extend_from_slice(format!("{}\n", big).as_bytes());
+        fs::write(&path, &content).unwrap();
+
+        let (saved, backup) = rewrite(&path, &CompactOptions { backup: true, ..opts(1024, false) }).unwrap();
+        let compacted = fs::read(&path).unwrap();
+        assert_eq!(compacted[..kept], content[..kept]);
+        assert_eq!(saved, (content.len() - compacted.len()) as u64);
+        assert!(String::from_utf8_lossy(&compacted[kept..]).contains("removed by chc compact"));
+
+        let backup = backup.unwrap();
+        assert_eq!(fs::read(&backup).unwrap(), content);
+        fs::remove_file(backup).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::{parse_date, parse_duration, Conversation};
+
+/// Settings from `config.toml`, every field falls back to the built-in default
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Transcripts modified within this window are treated as in use
+    #[serde(deserialize_with = "duration")]
+    pub active_window: Duration,
+    /// Longest title kept, longer ones are cut and end in `...`
+    pub title_length: usize,
+    /// Width of the interactive table, the TITLE column takes whatever the other columns leave
+    pub table_width: usize,
+    /// User messages that mark an agent transcript as a cache warmup rather than a conversation
+    pub warmup_messages: Vec<String>,
+    /// Named cleanup policies, run with `chc clean --profile <name>`
+    #[serde(rename = "profile")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Token prices by model name prefix for `chc stats`, on top of the built-in ones
+    pub prices: BTreeMap<String, Price>,
+    /// Extra rules for `chc secrets`, name to regex. A capture group narrows the secret down to part of the match.
+    pub secret_patterns: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            active_window: Duration::minutes(5),
+            title_length: 50,
+            table_width: 115,
+            warmup_messages: vec!["Warmup".to_string()],
+            profiles: BTreeMap::new(),
+            prices: BTreeMap::new(),
+            secret_patterns: BTreeMap::new(),
+        }
+    }
+}
+
+/// USD per million tokens
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Price {
+    pub input: f64,
+    pub output: f64,
+    pub cache_read: f64,
+    pub cache_write: f64,
+}
+
+/// A cleanup policy: the same selectors as the batch deletion flags
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    #[serde(deserialize_with = "optional_duration")]
+    pub older_than: Option<Duration>,
+    #[serde(deserialize_with = "optional_date")]
+    pub before: Option<DateTime<Utc>>,
+    /// Glob patterns (`*`, `?`) matched against the workspace path and folder name, empty for all workspaces
+    pub workspaces: Vec<String>,
+    pub delete_empty: bool,
+    pub delete_warmup: bool,
+}
+
+impl Profile {
+    /// Workspace globs only narrow a selection down, on their own they select nothing
+    pub fn selects_anything(&self) -> bool {
+        self.delete_empty || self.delete_warmup || self.older_than.is_some() || self.before.is_some()
+    }
+
+    pub fn matches_workspace(&self, conv: &Conversation) -> bool {
+        let folder = conv.workspace_folder.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        self.workspaces.is_empty()
+            || self.workspaces.iter().any(|p| glob_match(p, &conv.workspace_path) || glob_match(p, folder))
+    }
+}
+
+fn duration<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+    parse_duration(&String::deserialize(d)?).map_err(serde::de::Error::custom)
+}
+
+fn optional_duration<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+    duration(d).map(Some)
+}
+
+fn optional_date<'de, D: Deserializer<'de>>(d: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+    parse_date(&String::deserialize(d)?).map(Some).map_err(serde::de::Error::custom)
+}
+
+/// `*` matches any run of characters (including `/`), `?` any single one
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the text position it is currently stretched to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// `~/.config/chc/config.toml` on Linux, the platform's config directory elsewhere
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("chc").join("config.toml"))
+}
+
+fn read(path: &Path) -> Result<Config> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Invalid config file {}", path.display()))
+}
+
+/// Read `path`, or the default file if there is one. Without either the built-in defaults apply.
+pub fn load(path: Option<&Path>) -> Result<()> {
+    let config = match path {
+        Some(path) => read(path)?,
+        None => match default_path().filter(|p| p.exists()) {
+            Some(path) => read(&path)?,
+            None => Config::default(),
+        },
+    };
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_star_matches_any_run() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "/home/me/work"));
+        assert!(glob_match("/home/*/work", "/home/me/work"));
+        assert!(glob_match("/home/*", "/home/me/deep/work"), "`*` crosses `/`");
+        assert!(glob_match("*scratch*", "/tmp/scratch-1"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+    }
+
+    #[test]
+    fn glob_question_mark_matches_one_char() {
+        assert!(glob_match("/tmp/?", "/tmp/a"));
+        assert!(!glob_match("/tmp/?", "/tmp/"));
+        assert!(!glob_match("/tmp/?", "/tmp/ab"));
+        assert!(glob_match("/tmp/é?", "/tmp/éx"), "chars, not bytes");
+    }
+
+    #[test]
+    fn glob_is_anchored_at_both_ends() {
+        assert!(!glob_match("/tmp", "/tmp/a"));
+        assert!(!glob_match("tmp/a", "/tmp/a"));
+        assert!(glob_match("/tmp/a", "/tmp/a"));
+    }
+
+    #[test]
+    fn empty_glob_matches_only_empty_text() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "/tmp"));
+        assert!(glob_match("**", ""));
+    }
+}
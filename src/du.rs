@@ -0,0 +1,139 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{display_root, format_size, get_display_title, get_short_workspace, path_size, workspace, Conversation};
+
+/// Bytes used by one workspace folder, split by what they belong to
+#[derive(Debug, Default, Clone, Copy)]
+struct Usage {
+    /// Main `{sessionId}.jsonl` transcripts
+    main: u64,
+    /// Session folders minus their `subagents/`, plus any stray files
+    folders: u64,
+    /// `{sessionId}/subagents/` transcripts
+    subagents: u64,
+    /// Legacy `agent-*.jsonl` files at the workspace level
+    legacy: u64,
+}
+
+impl Usage {
+    fn total(&self) -> u64 {
+        self.main + self.folders + self.subagents + self.legacy
+    }
+
+    fn add(&mut self, other: &Usage) {
+        self.main += other.main;
+        self.folders += other.folders;
+        self.subagents += other.subagents;
+        self.legacy += other.legacy;
+    }
+}
+
+fn workspace_usage(workspace_folder: &Path) -> Result<Usage> {
+    let mut usage = Usage::default();
+
+    for entry in fs::read_dir(workspace_folder)?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            let subagents = path_size(&path.join("subagents"));
+            usage.subagents += subagents;
+            usage.folders += path_size(&path) - subagents;
+            continue;
+        }
+
+        let size = path_size(&path);
+        let is_jsonl = path.extension().and_then(|e| e.to_str()) == Some("jsonl");
+        let is_agent = path.file_stem().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("agent-"));
+        match (is_jsonl, is_agent) {
+            (true, true) => usage.legacy += size,
+            (true, false) => usage.main += size,
+            _ => usage.folders += size,
+        }
+    }
+
+    Ok(usage)
+}
+
+fn print_row(label: &str, usage: &Usage) {
+    println!(
+        "  {:>10} {:>10} {:>10} {:>10} {:>10}  {}",
+        format_size(usage.main),
+        format_size(usage.folders),
+        format_size(usage.subagents),
+        format_size(usage.legacy),
+        format_size(usage.total()).bold(),
+        label
+    );
+}
+
+/// Print disk usage per workspace, then the `top` largest conversations
+pub fn run(projects_dirs: &[PathBuf], workspace_filter: Option<&str>, conversations: &[Conversation], top: usize) -> Result<()> {
+    let mut workspaces: Vec<(String, Usage)> = Vec::new();
+
+    for projects_dir in projects_dirs {
+        let root = display_root(projects_dir.parent().unwrap_or(projects_dir));
+
+        for entry in fs::read_dir(projects_dir)?.filter_map(|e| e.ok()) {
+            let workspace_folder = entry.path();
+            if !workspace_folder.is_dir() { continue; }
+
+            let workspace_name = workspace_folder.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+            let workspace_path = workspace::resolve(&workspace_folder);
+            if let Some(filter) = workspace_filter {
+                if !workspace_path.contains(filter) && !workspace_name.contains(filter) {
+                    continue;
+                }
+            }
+
+            let label = if projects_dirs.len() > 1 { format!("{} [{}]", workspace_path, root) } else { workspace_path };
+            workspaces.push((label, workspace_usage(&workspace_folder)?));
+        }
+    }
+
+    if workspaces.is_empty() {
+        println!("{}", "No workspaces found.".yellow());
+        return Ok(());
+    }
+
+    workspaces.sort_by_key(|(_, usage)| std::cmp::Reverse(usage.total()));
+
+    println!("{}", "Disk usage by workspace:".bold().cyan());
+    println!();
+    println!(
+        "  {:>10} {:>10} {:>10} {:>10} {:>10}  {}",
+        "MAIN".dimmed(), "FOLDERS".dimmed(), "SUBAGENTS".dimmed(), "LEGACY".dimmed(), "TOTAL".dimmed(), "WORKSPACE".dimmed()
+    );
+
+    let mut totals = Usage::default();
+    for (label, usage) in &workspaces {
+        print_row(label, usage);
+        totals.add(usage);
+    }
+    println!("  {}", "-".repeat(60).dimmed());
+    print_row("total", &totals);
+
+    if top == 0 || conversations.is_empty() {
+        return Ok(());
+    }
+
+    let mut largest: Vec<&Conversation> = conversations.iter().collect();
+    largest.sort_by_key(|c| std::cmp::Reverse(c.total_size));
+
+    println!();
+    println!("{}", format!("Largest {} conversations:", top.min(largest.len())).bold().cyan());
+    println!();
+    for conv in largest.into_iter().take(top) {
+        let title: String = get_display_title(conv).chars().take(48).collect();
+        println!(
+            "  {:>10}  {:<48} {}  {}",
+            format_size(conv.total_size).bold(),
+            title,
+            get_short_workspace(&conv.workspace_path).cyan(),
+            conv.session_id.dimmed()
+        );
+    }
+
+    Ok(())
+}
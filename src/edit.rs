@@ -0,0 +1,449 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use colored::Colorize;
+use console::{Key, Term};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{backup_file, config, file_stamp, format_size, get_display_title, write_atomic_unchanged, Conversation};
+
+/// Strings longer than this are cut by `t`
+const TRUNCATE_CHARS: usize = 1000;
+/// Keys whose strings are never cut: IDs, block types, base64 data, and thinking,
+/// which the API checks against its signature when the conversation is resumed
+const KEEP_KEYS: &[&str] = &["id", "tool_use_id", "type", "media_type", "data", "thinking", "signature"];
+/// Keys that point at another entry's `uuid`, relinked when that entry is dropped
+const LINK_KEYS: &[&str] = &["parentUuid", "logicalParentUuid", "leafUuid"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mark {
+    Drop,
+    Truncate,
+}
+
+/// One JSONL line of the transcript
+struct Entry {
+    /// 1-based line number
+    line: usize,
+    /// The line as it is on disk, which need not be valid UTF-8
+    raw: Vec<u8>,
+    /// `None` for lines that aren't JSON, they are written back untouched
+    value: Option<Value>,
+    kind: String,
+    timestamp: Option<DateTime<Utc>>,
+    summary: String,
+    /// `tool_use` IDs this line calls or answers, a call and its result are dropped together
+    tool_ids: Vec<String>,
+}
+
+impl Entry {
+    fn uuid(&self) -> Option<&str> {
+        self.value.as_ref()?.get("uuid")?.as_str()
+    }
+
+    fn parent(&self) -> Option<&str> {
+        self.value.as_ref()?.get("parentUuid")?.as_str()
+    }
+
+    fn can_truncate(&self) -> bool {
+        self.value.as_ref().is_some_and(|v| truncate_entry(&mut v.clone()) > 0)
+    }
+}
+
+/// First line of the first content block, for the entry list
+fn summarize(value: &Value) -> String {
+    let content = value.pointer("/message/content");
+    let text = match content {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(blocks)) => blocks.first().map(|block| {
+            let get_str = |key: &str| block.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+            match block.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+                "text" => get_str("text"),
+                "thinking" => "[thinking]".to_string(),
+                "tool_use" => format!("[tool] {}", get_str("name")),
+                "tool_result" => match block.get("content") {
+                    Some(Value::String(s)) => format!("[result] {}", s),
+                    Some(Value::Array(items)) => format!("[result] {}", items.iter()
+                        .find_map(|i| i.get("text").and_then(|t| t.as_str())).unwrap_or("")),
+                    _ => "[result]".to_string(),
+                },
+                "image" => "[image]".to_string(),
+                other => format!("[{}]", other),
+            }
+        }).unwrap_or_default(),
+        _ => value.get("summary").or_else(|| value.get("content")).and_then(|s| s.as_str()).unwrap_or("").to_string(),
+    };
+    text.lines().find(|l| !l.trim().is_empty()).unwrap_or("").trim().to_string()
+}
+
+fn tool_ids(value: &Value) -> Vec<String> {
+    let Some(Value::Array(blocks)) = value.pointer("/message/content") else { return Vec::new() };
+    blocks.iter()
+        .filter_map(|b| match b.get("type").and_then(|t| t.as_str()) {
+            Some("tool_use") => b.get("id"),
+            Some("tool_result") => b.get("tool_use_id"),
+            _ => None,
+        })
+        .filter_map(|id| id.as_str().map(str::to_string))
+        .collect()
+}
+
+fn load(path: &Path) -> Result<Vec<Entry>> {
+    let content = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(parse(&content))
+}
+
+/// Lines that aren't valid UTF-8 are decoded lossily for display, and written back untouched unless edited
+fn parse(content: &[u8]) -> Vec<Entry> {
+    content.split(|&b| b == b'\n').enumerate()
+        .map(|(i, raw)| (i, raw.strip_suffix(b"\r").unwrap_or(raw)))
+        .filter(|(_, raw)| !raw.trim_ascii().is_empty())
+        .map(|(i, raw)| {
+            let value: Option<Value> = serde_json::from_str(&String::from_utf8_lossy(raw)).ok();
+            let get_str = |key: &str| value.as_ref().and_then(|v| v.get(key)).and_then(|v| v.as_str());
+            Entry {
+                line: i + 1,
+                raw: raw.to_vec(),
+                kind: get_str("type").unwrap_or(if value.is_some() { "?" } else { "invalid" }).to_string(),
+                timestamp: get_str("timestamp").and_then(|t| t.parse().ok()),
+                summary: value.as_ref().map(summarize).unwrap_or_default(),
+                tool_ids: value.as_ref().map(tool_ids).unwrap_or_default(),
+                value,
+            }
+        })
+        .collect()
+}
+
+/// Cut every long string under `value`, returns how many were cut
+fn truncate_strings(value: &mut Value) -> usize {
+    match value {
+        Value::String(s) => {
+            let chars = s.chars().count();
+            if chars <= TRUNCATE_CHARS {
+                return 0;
+            }
+            let kept: String = s.chars().take(TRUNCATE_CHARS).collect();
+            *s = format!("{}\n[... {} chars truncated by chc]", kept, chars - TRUNCATE_CHARS);
+            1
+        }
+        Value::Array(items) => items.iter_mut().map(truncate_strings).sum(),
+        Value::Object(map) => map.iter_mut()
+            .filter(|(key, _)| !KEEP_KEYS.contains(&key.as_str()))
+            .map(|(_, v)| truncate_strings(v))
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// The message content and its `toolUseResult` copy, the metadata around them stays as it is
+fn truncate_entry(value: &mut Value) -> usize {
+    let mut cut = 0;
+    if let Some(content) = value.pointer_mut("/message/content") {
+        cut += truncate_strings(content);
+    }
+    if let Some(result) = value.get_mut("toolUseResult") {
+        cut += truncate_strings(result);
+    }
+    cut
+}
+
+/// The transcript with the marks applied. Children of a dropped entry are relinked to its nearest kept ancestor,
+/// so the `parentUuid` chain Claude Code resumes from stays unbroken.
+fn apply(entries: &[Entry], marks: &BTreeMap<usize, Mark>) -> Result<Vec<u8>> {
+    let dropped: HashMap<&str, Option<&str>> = entries.iter().enumerate()
+        .filter(|(i, _)| marks.get(i) == Some(&Mark::Drop))
+        .filter_map(|(_, e)| Some((e.uuid()?, e.parent())))
+        .collect();
+    let resolve = |uuid: &str| -> Option<String> {
+        let mut uuid = uuid.to_string();
+        // Bounded in case a broken transcript links in a circle
+        for _ in 0..=dropped.len() {
+            match dropped.get(uuid.as_str()) {
+                Some(Some(parent)) => uuid = parent.to_string(),
+                Some(None) => return None,
+                None => return Some(uuid),
+            }
+        }
+        None
+    };
+
+    let mut out = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let mark = marks.get(&i).copied();
+        if mark == Some(Mark::Drop) {
+            continue;
+        }
+        let Some(mut value) = entry.value.clone() else {
+            out.extend_from_slice(&entry.raw);
+            out.push(b'\n');
+            continue;
+        };
+
+        let mut changed = false;
+        for key in LINK_KEYS {
+            let Some(target) = value.get(*key).and_then(|v| v.as_str()) else { continue };
+            if dropped.contains_key(target) {
+                value[*key] = resolve(target).map(Value::String).unwrap_or(Value::Null);
+                changed = true;
+            }
+        }
+        if mark == Some(Mark::Truncate) {
+            changed |= truncate_entry(&mut value) > 0;
+        }
+
+        if changed {
+            out.extend_from_slice(serde_json::to_string(&value)?.as_bytes());
+        } else {
+            out.extend_from_slice(&entry.raw);
+        }
+        out.push(b'\n');
+    }
+    Ok(out)
+}
+
+/// Entries sharing a tool call with `index`: dropping only one side would leave a `tool_result`
+/// without its `tool_use` (or the reverse), which the API rejects on resume
+fn tool_group(entries: &[Entry], index: usize) -> Vec<usize> {
+    let ids: HashSet<&String> = entries[index].tool_ids.iter().collect();
+    entries.iter().enumerate()
+        .filter(|(i, e)| *i == index || e.tool_ids.iter().any(|id| ids.contains(id)))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn row(entry: &Entry, mark: Option<Mark>, width: usize) -> String {
+    let marker = match mark {
+        Some(Mark::Drop) => "[D]",
+        Some(Mark::Truncate) => "[T]",
+        None => "[ ]",
+    };
+    let time = entry.timestamp.map(|t| t.with_timezone(&Local).format("%m-%d %H:%M").to_string()).unwrap_or_default();
+    let prefix = format!("{} {:>6} {:<10} {:<11} {:>9} ", marker, format!("L{}", entry.line), entry.kind, time, format_size(entry.raw.len() as u64));
+    let room = width.saturating_sub(prefix.chars().count() + 1);
+    let summary: String = entry.summary.chars().take(room).collect();
+    format!("{}{}", prefix, summary)
+}
+
+/// Ask a yes/no question on the status line
+fn ask(term: &Term, question: &str) -> Result<bool> {
+    println!("{} {}", question.red().bold(), "[y/N]".dimmed());
+    Ok(matches!(term.read_key()?, Key::Char('y') | Key::Char('Y')))
+}
+
+/// Pick transcript lines to drop or truncate, then back the file up and rewrite it atomically.
+/// Returns the backup if the transcript was changed.
+pub fn run(term: &Term, conv: &Conversation) -> Result<Option<PathBuf>> {
+    let loaded = file_stamp(&conv.path);
+    let entries = load(&conv.path)?;
+    let mut marks: BTreeMap<usize, Mark> = BTreeMap::new();
+    let mut cursor: usize = 0;
+    let mut top: usize = 0;
+    let mut status: Option<String> = None;
+
+    loop {
+        let (height, width) = term.size();
+        let page = (height as usize).saturating_sub(6).max(1);
+        if cursor < top {
+            top = cursor;
+        } else if cursor >= top + page {
+            top = cursor + 1 - page;
+        }
+
+        let _ = term.move_cursor_to(0, 0);
+        let _ = term.clear_screen();
+
+        println!("{} {}", "Edit:".bold().cyan(), get_display_title(conv).bold());
+        println!("{}", format!("{} lines, {}", entries.len(), conv.path.display()).dimmed());
+        println!();
+        for (i, entry) in entries.iter().enumerate().skip(top).take(page) {
+            let line = row(entry, marks.get(&i).copied(), width as usize);
+            match marks.get(&i) {
+                _ if i == cursor => println!("{}", line.on_bright_black().bold()),
+                Some(Mark::Drop) => println!("{}", line.red().strikethrough()),
+                Some(Mark::Truncate) => println!("{}", line.yellow()),
+                None => println!("{}", line),
+            }
+        }
+        println!("{}", "-".repeat(config::get().table_width).dimmed());
+
+        let drops = marks.values().filter(|&&m| m == Mark::Drop).count();
+        let cuts = marks.len() - drops;
+        if let Some(msg) = status.take() {
+            println!("{}", msg.yellow());
+        } else {
+            println!(
+                "{} {} {} {} {} {}",
+                format!("{} drop, {} truncate", drops, cuts).dimmed(),
+                "[j/k]Move".dimmed(),
+                "[d]Drop".dimmed(),
+                format!("[t]Truncate to {} chars", TRUNCATE_CHARS).dimmed(),
+                "[w]Write".dimmed(),
+                "[q]Back".dimmed()
+            );
+        }
+
+        match term.read_key()? {
+            Key::ArrowUp | Key::Char('k') => cursor = cursor.saturating_sub(1),
+            Key::ArrowDown | Key::Char('j') => cursor = (cursor + 1).min(entries.len().saturating_sub(1)),
+            Key::PageUp => cursor = cursor.saturating_sub(page),
+            Key::PageDown => cursor = (cursor + page).min(entries.len().saturating_sub(1)),
+            Key::Home | Key::Char('g') => cursor = 0,
+            Key::End | Key::Char('G') => cursor = entries.len().saturating_sub(1),
+            Key::Char('d') if !entries.is_empty() => {
+                let group = tool_group(&entries, cursor);
+                if marks.get(&cursor) == Some(&Mark::Drop) {
+                    group.iter().for_each(|i| { marks.remove(i); });
+                } else {
+                    group.iter().for_each(|&i| { marks.insert(i, Mark::Drop); });
+                }
+                if group.len() > 1 {
+                    let lines: Vec<String> = group.iter().filter(|&&i| i != cursor).map(|&i| format!("L{}", entries[i].line)).collect();
+                    status = Some(format!("Also toggled {}, the other half of its tool calls", lines.join(", ")));
+                }
+            }
+            Key::Char('t') if !entries.is_empty() => {
+                if marks.get(&cursor) == Some(&Mark::Truncate) {
+                    marks.remove(&cursor);
+                } else if entries[cursor].can_truncate() {
+                    marks.insert(cursor, Mark::Truncate);
+                } else {
+                    status = Some(format!("Nothing in L{} is longer than {} chars", entries[cursor].line, TRUNCATE_CHARS));
+                }
+            }
+            Key::Char('w') if !marks.is_empty() => {
+                if !ask(term, &format!("Rewrite the transcript, dropping {} and truncating {} lines?", drops, cuts))? {
+                    continue;
+                }
+                // Claude Code may have appended to it meanwhile, those lines would be lost
+                let changed = "The transcript changed on disk since it was opened, nothing was written. Reopen the editor.";
+                if file_stamp(&conv.path) != loaded {
+                    status = Some(changed.to_string());
+                    continue;
+                }
+                let content = apply(&entries, &marks)?;
+                let backup = backup_file(&conv.path)?;
+                if !write_atomic_unchanged(&conv.path, &content, loaded)? {
+                    if let Some(backup) = backup {
+                        let _ = fs::remove_file(backup);
+                    }
+                    status = Some(changed.to_string());
+                    continue;
+                }
+                return Ok(backup);
+            }
+            Key::Char('q') | Key::Escape if marks.is_empty() || ask(term, &format!("Discard {} pending changes?", marks.len()))? => {
+                return Ok(None);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn line(uuid: &str, parent: Option<&str>) -> Value {
+        json!({"type": "user", "uuid": uuid, "parentUuid": parent, "message": {"role": "user", "content": uuid}})
+    }
+
+    fn transcript(lines: &[Value]) -> Vec<Entry> {
+        parse(lines.iter().map(|l| l.to_string()).collect::<Vec<_>>().join("\n").as_bytes())
+    }
+
+    fn applied(entries: &[Entry], marks: &BTreeMap<usize, Mark>) -> String {
+        String::from_utf8(apply(entries, marks).unwrap()).unwrap()
+    }
+
+    fn drop_lines(indices: &[usize]) -> BTreeMap<usize, Mark> {
+        indices.iter().map(|&i| (i, Mark::Drop)).collect()
+    }
+
+    /// `(uuid, parentUuid)` of each line of the applied transcript
+    fn links(out: &str) -> Vec<(String, Option<String>)> {
+        out.lines()
+            .map(|l| serde_json::from_str::<Value>(l).unwrap())
+            .map(|v| (v["uuid"].as_str().unwrap().to_string(), v["parentUuid"].as_str().map(str::to_string)))
+            .collect()
+    }
+
+    fn link(uuid: &str, parent: Option<&str>) -> (String, Option<String>) {
+        (uuid.to_string(), parent.map(str::to_string))
+    }
+
+    #[test]
+    fn dropping_a_middle_entry_relinks_its_child() {
+        let entries = transcript(&[line("a", None), line("b", Some("a")), line("c", Some("b"))]);
+        let out = applied(&entries, &drop_lines(&[1]));
+
+        assert_eq!(links(&out), vec![link("a", None), link("c", Some("a"))]);
+    }
+
+    #[test]
+    fn dropping_a_chain_relinks_to_the_nearest_kept_ancestor() {
+        let entries = transcript(&[line("a", None), line("b", Some("a")), line("c", Some("b")), line("d", Some("c"))]);
+        let out = applied(&entries, &drop_lines(&[1, 2]));
+
+        assert_eq!(links(&out), vec![link("a", None), link("d", Some("a"))]);
+    }
+
+    #[test]
+    fn dropping_the_root_makes_its_child_the_root() {
+        let entries = transcript(&[line("a", None), line("b", Some("a")), line("c", Some("b"))]);
+        let out = applied(&entries, &drop_lines(&[0]));
+
+        assert_eq!(links(&out), vec![link("b", None), link("c", Some("b"))]);
+        assert!(out.lines().next().unwrap().contains(r#""parentUuid":null"#));
+    }
+
+    #[test]
+    fn leaf_and_logical_parent_links_are_relinked() {
+        let mut boundary = line("c", None);
+        boundary["logicalParentUuid"] = json!("b");
+        let summary = json!({"type": "summary", "summary": "s", "leafUuid": "b"});
+        let entries = transcript(&[line("a", None), line("b", Some("a")), boundary, summary]);
+        let out = applied(&entries, &drop_lines(&[1]));
+
+        let values: Vec<Value> = out.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(values[1]["logicalParentUuid"], "a");
+        assert_eq!(values[2]["leafUuid"], "a");
+    }
+
+    #[test]
+    fn untouched_lines_are_written_back_as_they_were() {
+        let raw = r#"{"uuid":"a",  "parentUuid":null}"#;
+        let entries = parse(format!("{}\nnot json\n{}", raw, line("b", Some("a"))).as_bytes());
+        let out = applied(&entries, &BTreeMap::new());
+
+        assert_eq!(out.lines().take(2).collect::<Vec<_>>(), vec![raw, "not json"]);
+    }
+
+    #[test]
+    fn invalid_utf8_lines_are_kept_byte_for_byte() {
+        let mut content = line("a", None).to_string().into_bytes();
+        content.extend_from_slice(b"\n{\"type\":\"user\",\"uuid\":\"b\",\"parentUuid\":\"a\",\"message\":{\"content\":\"caf\xe9\"}}\n");
+        content.extend_from_slice(line("c", Some("b")).to_string().as_bytes());
+        let entries = parse(&content);
+        assert_eq!(entries[1].kind, "user");
+        assert_eq!(entries[1].summary, "caf\u{fffd}");
+
+        let out = apply(&entries, &drop_lines(&[2])).unwrap();
+        let kept = content.iter().rposition(|&b| b == b'\n').unwrap() + 1;
+        assert_eq!(out, content[..kept]);
+    }
+
+    #[test]
+    fn tool_group_pairs_a_call_with_its_result() {
+        let call = json!({"type": "assistant", "uuid": "b", "message": {"content": [{"type": "tool_use", "id": "t1", "name": "Bash"}]}});
+        let result = json!({"type": "user", "uuid": "c", "message": {"content": [{"type": "tool_result", "tool_use_id": "t1"}]}});
+        let other = json!({"type": "user", "uuid": "d", "message": {"content": [{"type": "tool_result", "tool_use_id": "t2"}]}});
+        let entries = transcript(&[line("a", None), call, result, other]);
+
+        assert_eq!(tool_group(&entries, 1), vec![1, 2]);
+        assert_eq!(tool_group(&entries, 2), vec![1, 2]);
+        assert_eq!(tool_group(&entries, 0), vec![0]);
+    }
+}
@@ -0,0 +1,380 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::Serialize;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::{get_display_title, subagent_paths, Conversation, JsonlEntry};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// Markdown, subagents in collapsible <details> sections
+    Md,
+    /// Standalone HTML page
+    Html,
+    /// Structured JSON
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Text { text: String },
+    Thinking { text: String },
+    ToolUse { name: String, input: serde_json::Value },
+    ToolResult { content: String, is_error: bool },
+    Image,
+}
+
+impl Block {
+    /// Plain text of the block, as searched by `chc search`
+    pub fn plain_text(&self) -> String {
+        match self {
+            Block::Text { text } | Block::Thinking { text } => text.clone(),
+            Block::ToolUse { name, input } => format!("{} {}", name, input),
+            Block::ToolResult { content, .. } => content.clone(),
+            Block::Image => "[image]".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Turn {
+    /// 1-based line number in the JSONL file
+    pub line: usize,
+    pub role: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Serialize)]
+struct Subagent {
+    name: String,
+    turns: Vec<Turn>,
+}
+
+#[derive(Debug, Serialize)]
+struct Transcript {
+    session_id: String,
+    workspace_path: String,
+    title: String,
+    turns: Vec<Turn>,
+    subagents: Vec<Subagent>,
+}
+
+/// Text of a tool_result `content`, which is either a string or a list of text blocks
+fn tool_result_text(content: Option<&serde_json::Value>) -> String {
+    match content {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Array(arr)) => arr.iter()
+            .map(|item| match item.get("type").and_then(|v| v.as_str()) {
+                Some("text") => item.get("text").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                Some("image") => "[image]".to_string(),
+                _ => String::new(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn parse_blocks(content: &serde_json::Value) -> Vec<Block> {
+    let serde_json::Value::Array(arr) = content else {
+        return match content.as_str() {
+            Some(text) if !text.is_empty() => vec![Block::Text { text: text.to_string() }],
+            _ => Vec::new(),
+        };
+    };
+
+    arr.iter()
+        .filter_map(|item| {
+            let get_str = |key: &str| item.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+            match item.get("type").and_then(|v| v.as_str())? {
+                "text" => Some(Block::Text { text: get_str("text") }),
+                "thinking" => Some(Block::Thinking { text: get_str("thinking") }),
+                "tool_use" => Some(Block::ToolUse {
+                    name: get_str("name"),
+                    input: item.get("input").cloned().unwrap_or(serde_json::Value::Null),
+                }),
+                "tool_result" => Some(Block::ToolResult {
+                    content: tool_result_text(item.get("content")),
+                    is_error: item.get("is_error").and_then(|v| v.as_bool()).unwrap_or(false),
+                }),
+                "image" => Some(Block::Image),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Read the user and assistant turns of a transcript
+pub fn parse_turns(path: &Path) -> Result<Vec<Turn>> {
+    Ok(read_turns(path, usize::MAX)?.turns)
+}
+
+/// The first turns of a transcript, as `read_turns` found them
+pub struct TurnPage {
+    pub turns: Vec<Turn>,
+    /// Whether the transcript has turns past the limit
+    pub more: bool,
+    /// Non-empty lines in the whole transcript
+    pub entries: usize,
+}
+
+/// Read the first `limit` turns of a transcript in one pass. Past the limit lines are only counted,
+/// and parsed just until one shows there are more turns.
+pub fn read_turns(path: &Path, limit: usize) -> Result<TurnPage> {
+    let file = fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut page = TurnPage { turns: Vec::new(), more: false, entries: 0 };
+
+    // Split on bytes, so a line that isn't valid UTF-8 is decoded lossily instead of failing the read
+    for (i, line) in BufReader::new(file).split(b'\n').enumerate() {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+        page.entries += 1;
+        if page.more {
+            continue;
+        }
+
+        let Ok(entry) = serde_json::from_str::<JsonlEntry>(&String::from_utf8_lossy(&line)) else { continue };
+        let role = match entry.entry_type.as_deref() {
+            Some(role @ ("user" | "assistant")) => role.to_string(),
+            _ => continue,
+        };
+        let Some(content) = entry.message.and_then(|m| m.content) else { continue };
+        let blocks = parse_blocks(&content);
+        if blocks.is_empty() {
+            continue;
+        }
+        if page.turns.len() == limit {
+            page.more = true;
+            continue;
+        }
+        page.turns.push(Turn {
+            line: i + 1,
+            role,
+            timestamp: entry.timestamp.and_then(|ts| ts.parse().ok()),
+            blocks,
+        });
+    }
+
+    Ok(page)
+}
+
+pub fn format_time(ts: Option<DateTime<Utc>>) -> String {
+    ts.map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+pub fn role_label(role: &str) -> &'static str {
+    if role == "user" { "User" } else { "Assistant" }
+}
+
+/// A code fence longer than any backtick run inside `text`
+fn fence(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    "`".repeat((longest + 1).max(3))
+}
+
+fn render_markdown_turns(out: &mut String, turns: &[Turn], heading: &str) {
+    for turn in turns {
+        out.push_str(&format!("{} {} · {}\n\n", heading, role_label(&turn.role), format_time(turn.timestamp)));
+        for block in &turn.blocks {
+            match block {
+                Block::Text { text } => out.push_str(&format!("{}\n\n", text.trim_end())),
+                Block::Thinking { text } => {
+                    out.push_str(&format!("<details>\n<summary>Thinking</summary>\n\n{}\n\n</details>\n\n", text.trim_end()));
+                }
+                Block::ToolUse { name, input } => {
+                    let input = serde_json::to_string_pretty(input).unwrap_or_default();
+                    let f = fence(&input);
+                    out.push_str(&format!("**Tool call: `{}`**\n\n{}json\n{}\n{}\n\n", name, f, input, f));
+                }
+                Block::ToolResult { content, is_error } => {
+                    let label = if *is_error { "Tool error" } else { "Tool result" };
+                    let f = fence(content);
+                    out.push_str(&format!(
+                        "<details>\n<summary>{}</summary>\n\n{}\n{}\n{}\n\n</details>\n\n",
+                        label, f, content.trim_end(), f
+                    ));
+                }
+                Block::Image => out.push_str("*[image]*\n\n"),
+            }
+        }
+    }
+}
+
+fn render_markdown(t: &Transcript) -> String {
+    let mut out = format!("# {}\n\n", t.title);
+    out.push_str(&format!("- Session: `{}`\n- Project: `{}`\n", t.session_id, t.workspace_path));
+    if let (Some(first), Some(last)) = (t.turns.first(), t.turns.last()) {
+        out.push_str(&format!("- Time: {} → {}\n", format_time(first.timestamp), format_time(last.timestamp)));
+    }
+    out.push_str("\n---\n\n");
+
+    render_markdown_turns(&mut out, &t.turns, "###");
+
+    if !t.subagents.is_empty() {
+        out.push_str("## Subagents\n\n");
+        for agent in &t.subagents {
+            out.push_str(&format!("<details>\n<summary>{} ({} turns)</summary>\n\n", agent.name, agent.turns.len()));
+            render_markdown_turns(&mut out, &agent.turns, "####");
+            out.push_str("</details>\n\n");
+        }
+    }
+
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn render_html_turns(out: &mut String, turns: &[Turn]) {
+    for turn in turns {
+        out.push_str(&format!(
+            "<div class=\"turn {}\">\n<div class=\"meta\">{} · {}</div>\n",
+            turn.role, role_label(&turn.role), format_time(turn.timestamp)
+        ));
+        for block in &turn.blocks {
+            match block {
+                Block::Text { text } => out.push_str(&format!("<pre class=\"text\">{}</pre>\n", escape_html(text.trim_end()))),
+                Block::Thinking { text } => out.push_str(&format!(
+                    "<details><summary>Thinking</summary><pre>{}</pre></details>\n",
+                    escape_html(text.trim_end())
+                )),
+                Block::ToolUse { name, input } => out.push_str(&format!(
+                    "<div class=\"tool\">Tool call: <code>{}</code><pre>{}</pre></div>\n",
+                    escape_html(name),
+                    escape_html(&serde_json::to_string_pretty(input).unwrap_or_default())
+                )),
+                Block::ToolResult { content, is_error } => out.push_str(&format!(
+                    "<details class=\"tool\"><summary>{}</summary><pre>{}</pre></details>\n",
+                    if *is_error { "Tool error" } else { "Tool result" },
+                    escape_html(content.trim_end())
+                )),
+                Block::Image => out.push_str("<p><em>[image]</em></p>\n"),
+            }
+        }
+        out.push_str("</div>\n");
+    }
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;max-width:960px;margin:2em auto;padding:0 1em}\
+pre{white-space:pre-wrap;word-wrap:break-word}\
+.turn{border-left:3px solid #ccc;padding:0 1em;margin:1em 0}\
+.turn.user{border-color:#3b82f6}.turn.assistant{border-color:#10b981}\
+.meta{color:#888;font-size:.85em}.tool{background:#f6f6f6;padding:.3em .6em;margin:.4em 0}\
+pre.text{font-family:inherit}";
+
+fn render_html(t: &Transcript) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape_html(&t.title), HTML_STYLE
+    );
+    out.push_str(&format!("<h1>{}</h1>\n", escape_html(&t.title)));
+    out.push_str(&format!(
+        "<p class=\"meta\">Session <code>{}</code> · {}</p>\n",
+        escape_html(&t.session_id), escape_html(&t.workspace_path)
+    ));
+
+    render_html_turns(&mut out, &t.turns);
+
+    if !t.subagents.is_empty() {
+        out.push_str("<h2>Subagents</h2>\n");
+        for agent in &t.subagents {
+            out.push_str(&format!("<details>\n<summary>{} ({} turns)</summary>\n", escape_html(&agent.name), agent.turns.len()));
+            render_html_turns(&mut out, &agent.turns);
+            out.push_str("</details>\n");
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+pub fn export(conv: &Conversation, format: ExportFormat, output: Option<&PathBuf>) -> Result<()> {
+    let mut subagents = Vec::new();
+    for path in subagent_paths(conv)? {
+        subagents.push(Subagent {
+            name: path.file_stem().and_then(|n| n.to_str()).unwrap_or("").to_string(),
+            turns: parse_turns(&path)?,
+        });
+    }
+
+    let transcript = Transcript {
+        session_id: conv.session_id.clone(),
+        workspace_path: conv.workspace_path.clone(),
+        title: get_display_title(conv),
+        turns: if conv.is_empty { Vec::new() } else { parse_turns(&conv.path)? },
+        subagents,
+    };
+
+    let rendered = match format {
+        ExportFormat::Md => render_markdown(&transcript),
+        ExportFormat::Html => render_html(&transcript),
+        ExportFormat::Json => serde_json::to_string_pretty(&transcript)? + "\n",
+    };
+
+    match output {
+        Some(path) => {
+            fs::write(path, rendered).with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!("{} Exported {} to {}", "OK".green(), conv.session_id, path.display());
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{temp_claude_dir, write_transcript};
+
+    #[test]
+    fn invalid_utf8_line_keeps_the_other_turns() {
+        let dir = temp_claude_dir("export-invalid-utf8");
+        let path = write_transcript(&dir, "-tmp-ws", "export-invalid-utf8-1", &["first", "second"]);
+        let mut bytes = fs::read(&path).unwrap();
+        let second = bytes.iter().position(|&b| b == b'\n').unwrap() + 1;
+        bytes.splice(second..second, b"{\"type\":\"user\",\"message\":{\"content\":\"bad \xff byte\"}}\n".iter().copied());
+        fs::write(&path, bytes).unwrap();
+
+        let turns = parse_turns(&path).unwrap();
+        let texts: Vec<&str> = turns.iter()
+            .map(|t| match &t.blocks[0] { Block::Text { text } => text.as_str(), _ => "" })
+            .collect();
+        assert_eq!(texts, ["first", "bad \u{fffd} byte", "second"]);
+        assert_eq!(turns[2].line, 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn read_turns_stops_at_the_limit_but_counts_every_entry() {
+        let dir = temp_claude_dir("export-read-turns");
+        let path = write_transcript(&dir, "-tmp-ws", "export-read-turns-1", &["one", "two", "three"]);
+        let mut content = fs::read(&path).unwrap();
+        content.extend_from_slice(b"{\"type\":\"summary\",\"summary\":\"s\"}\n\n");
+        fs::write(&path, content).unwrap();
+
+        let page = read_turns(&path, 2).unwrap();
+        assert_eq!(page.turns.iter().map(|t| t.line).collect::<Vec<_>>(), [1, 2]);
+        assert!(page.more);
+        assert_eq!(page.entries, 4);
+
+        let page = read_turns(&path, 3).unwrap();
+        assert_eq!(page.turns.len(), 3);
+        assert!(!page.more);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,461 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::workspace::{self, encode_project_path};
+use crate::{backup_file, confirm_deletion, file_stamp, prune_backups, write_atomic_unchanged, JsonlEntry};
+
+/// One line of `~/.claude/history.jsonl` (the prompt history index)
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryEntry {
+    display: String,
+    #[serde(rename = "pastedContents", default)]
+    pasted_contents: serde_json::Value,
+    timestamp: Option<i64>,
+    project: Option<String>,
+    #[serde(rename = "sessionId", skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+}
+
+fn history_path(claude_dir: &Path) -> PathBuf {
+    claude_dir.join("history.jsonl")
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(content.lines().filter(|l| !l.trim().is_empty()).map(str::to_string).collect())
+}
+
+/// How often `update` starts over before giving up on an index that keeps changing
+const UPDATE_ATTEMPTS: usize = 5;
+/// Backups of the index kept in the backups folder, every rewrite adds one
+const INDEX_BACKUPS: usize = 10;
+
+/// Rewrite the index with `change`, which returns `None` to leave it alone. Claude appends to the index
+/// while it runs, so if the file changed between reading it and replacing it the new lines are prepared
+/// again from the new content rather than dropping what was appended. Returns the backup path, if any.
+fn update(path: &Path, mut change: impl FnMut(Vec<String>) -> Option<Vec<String>>) -> Result<Option<PathBuf>> {
+    for _ in 0..UPDATE_ATTEMPTS {
+        let stamp = file_stamp(path);
+        let lines = if path.exists() { read_lines(path)? } else { Vec::new() };
+        let Some(new_lines) = change(lines) else { return Ok(None) };
+        let mut content = new_lines.join("\n");
+        if !content.is_empty() {
+            content.push('\n');
+        }
+
+        let backup = backup_file(path)?;
+        if write_atomic_unchanged(path, content.as_bytes(), stamp)? {
+            prune_backups(path, INDEX_BACKUPS)?;
+            return Ok(backup);
+        }
+        if let Some(backup) = backup {
+            let _ = fs::remove_file(backup);
+        }
+    }
+    anyhow::bail!("{} kept changing while it was being rewritten, try again later", path.display())
+}
+
+fn line_session_id(line: &str) -> Option<String> {
+    serde_json::from_str::<HistoryEntry>(line).ok()?.session_id
+}
+
+fn line_timestamp(line: &str) -> Option<i64> {
+    serde_json::from_str::<HistoryEntry>(line).ok()?.timestamp
+}
+
+fn is_session_line(line: &str, session_id: &str) -> bool {
+    line.contains(session_id) && line_session_id(line).as_deref() == Some(session_id)
+}
+
+/// Index entries of a session, without touching the index
+pub fn session_lines(claude_dir: &Path, session_id: &str) -> Result<Vec<String>> {
+    let path = history_path(claude_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(read_lines(&path)?.into_iter().filter(|l| is_session_line(l, session_id)).collect())
+}
+
+/// Split the entries of `session_ids` out of the index, returns the kept lines and the removed ones by session
+fn split_sessions(lines: Vec<String>, session_ids: &HashSet<&str>) -> (Vec<String>, HashMap<String, Vec<String>>) {
+    let mut kept = Vec::new();
+    let mut removed: HashMap<String, Vec<String>> = HashMap::new();
+    for line in lines {
+        match line_session_id(&line).filter(|id| session_ids.contains(id.as_str())) {
+            Some(id) => removed.entry(id).or_default().push(line),
+            None => kept.push(line),
+        }
+    }
+    (kept, removed)
+}
+
+/// Remove the index entries of deleted sessions in a single rewrite.
+/// Returns the removed lines of each session so they can be put back later.
+pub fn remove_sessions(claude_dir: &Path, session_ids: &[String]) -> Result<HashMap<String, Vec<String>>> {
+    let path = history_path(claude_dir);
+    let ids: HashSet<&str> = session_ids.iter().map(String::as_str).collect();
+    let mut removed = HashMap::new();
+    if !path.exists() || ids.is_empty() {
+        return Ok(removed);
+    }
+
+    update(&path, |lines| {
+        let (kept, split) = split_sessions(lines, &ids);
+        removed = split;
+        (!removed.is_empty()).then_some(kept)
+    })?;
+    Ok(removed)
+}
+
+/// Insert `restored` into `lines` by timestamp, entries without one go last
+fn insert_by_timestamp(lines: &mut Vec<String>, restored: &[String]) {
+    for line in restored {
+        let ts = line_timestamp(line).unwrap_or(i64::MAX);
+        let pos = lines.iter()
+            .position(|l| line_timestamp(l).is_some_and(|t| t > ts))
+            .unwrap_or(lines.len());
+        lines.insert(pos, line.clone());
+    }
+}
+
+/// Put previously removed entries back, keeping the index in timestamp order
+pub fn restore_lines(claude_dir: &Path, restored: &[String]) -> Result<()> {
+    if restored.is_empty() {
+        return Ok(());
+    }
+
+    update(&history_path(claude_dir), |mut lines| {
+        insert_by_timestamp(&mut lines, restored);
+        Some(lines)
+    })?;
+    Ok(())
+}
+
+/// Session IDs and workspace folder names that actually exist under `projects/`
+fn existing_sessions(projects_dir: &Path) -> Result<(HashSet<String>, HashSet<String>)> {
+    let mut sessions = HashSet::new();
+    let mut workspaces = HashSet::new();
+
+    for entry in fs::read_dir(projects_dir)?.filter_map(|e| e.ok()) {
+        let workspace_folder = entry.path();
+        if !workspace_folder.is_dir() { continue; }
+        workspaces.insert(entry.file_name().to_string_lossy().to_string());
+
+        for file_entry in fs::read_dir(&workspace_folder)?.filter_map(|e| e.ok()) {
+            let path = file_entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") { continue; }
+            let name = path.file_stem().and_then(|n| n.to_str()).unwrap_or("");
+            if !name.starts_with("agent-") {
+                sessions.insert(name.to_string());
+            }
+        }
+    }
+
+    Ok((sessions, workspaces))
+}
+
+/// Full text of a user prompt, skipping tool results and IDE context blocks
+fn prompt_text(content: &serde_json::Value) -> String {
+    match content {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(arr) => arr.iter()
+            .filter(|item| item.get("type").and_then(|v| v.as_str()) == Some("text"))
+            .filter_map(|item| item.get("text").and_then(|v| v.as_str()))
+            .filter(|text| !text.starts_with("<ide_"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// User lines that Claude writes itself rather than the user typing them
+const NOT_PROMPTS: &[&str] = &["<command-name>", "<command-message>", "<local-command-stdout>", "[Request interrupted by user"];
+
+/// Regenerate index entries from the user prompts in every main transcript
+fn rebuild_entries(projects_dir: &Path) -> Result<Vec<String>> {
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+
+    for entry in fs::read_dir(projects_dir)?.filter_map(|e| e.ok()) {
+        let workspace_folder = entry.path();
+        if !workspace_folder.is_dir() { continue; }
+        let workspace_path = workspace::resolve(&workspace_folder);
+
+        for file_entry in fs::read_dir(&workspace_folder)?.filter_map(|e| e.ok()) {
+            let path = file_entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") { continue; }
+            let session_id = path.file_stem().and_then(|n| n.to_str()).unwrap_or("").to_string();
+            if session_id.starts_with("agent-") { continue; }
+
+            let Ok(content) = fs::read_to_string(&path) else { continue };
+            for line in content.lines() {
+                let Ok(entry) = serde_json::from_str::<JsonlEntry>(line) else { continue };
+                if entry.entry_type.as_deref() != Some("user") || entry.is_meta { continue; }
+                let Some(text) = entry.message.and_then(|m| m.content).map(|c| prompt_text(&c)) else { continue };
+                if text.trim().is_empty() || config::get().warmup_messages.contains(&text) { continue; }
+                if NOT_PROMPTS.iter().any(|p| text.starts_with(p)) { continue; }
+
+                let timestamp = entry.timestamp
+                    .and_then(|ts| ts.parse::<DateTime<Utc>>().ok())
+                    .map(|dt| dt.timestamp_millis());
+                entries.push(HistoryEntry {
+                    display: text,
+                    pasted_contents: serde_json::json!({}),
+                    timestamp,
+                    project: Some(entry.cwd.unwrap_or_else(|| workspace_path.clone())),
+                    session_id: Some(session_id.clone()),
+                });
+            }
+        }
+    }
+
+    entries.sort_by_key(|e| e.timestamp.unwrap_or(0));
+    entries.iter().map(|e| serde_json::to_string(e).map_err(Into::into)).collect()
+}
+
+/// The prompt an index entry was made from: `display` with its `[Pasted text #1 +20 lines]` placeholders filled in
+fn expanded_display(entry: &HistoryEntry) -> String {
+    let mut text = entry.display.clone();
+    let Some(pasted) = entry.pasted_contents.as_object() else { return text };
+    for (id, paste) in pasted {
+        let Some(content) = paste.get("content").and_then(|c| c.as_str()) else { continue };
+        let marker = format!("[Pasted text #{}", id);
+        if let Some(start) = text.find(&marker) {
+            if let Some(len) = text[start..].find(']') {
+                text.replace_range(start..start + len + 1, content);
+            }
+        }
+    }
+    text
+}
+
+/// Rebuilt lines, reusing the existing line of each prompt the index still has so its `pastedContents` survive
+fn merge_rebuilt(rebuilt: &[String], lines: &[String]) -> Vec<String> {
+    let mut existing: HashMap<(String, String), &String> = HashMap::new();
+    for line in lines {
+        let Ok(entry) = serde_json::from_str::<HistoryEntry>(line) else { continue };
+        let Some(session_id) = entry.session_id.clone() else { continue };
+        existing.entry((session_id, expanded_display(&entry))).or_insert(line);
+    }
+
+    rebuilt.iter()
+        .map(|line| {
+            let existing_line = serde_json::from_str::<HistoryEntry>(line).ok()
+                .and_then(|e| existing.get(&(e.session_id.unwrap_or_default(), e.display)).copied());
+            existing_line.unwrap_or(line).clone()
+        })
+        .collect()
+}
+
+/// Index lines whose conversation still exists under `projects/`
+fn prune(lines: &[String], sessions: &HashSet<String>, workspaces: &HashSet<String>) -> Vec<String> {
+    lines.iter()
+        .filter(|line| match serde_json::from_str::<HistoryEntry>(line) {
+            Ok(HistoryEntry { session_id: Some(id), .. }) => sessions.contains(&id),
+            // Older entries have no sessionId, keep them as long as their project still has transcripts
+            Ok(HistoryEntry { project: Some(project), .. }) => workspaces.contains(&encode_project_path(&project)),
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Prune stale entries from `history.jsonl`, or rebuild it from the transcripts under `projects/`
+pub fn reindex(projects_dir: &Path, rebuild: bool, dry_run: bool, yes: bool) -> Result<()> {
+    let claude_dir = projects_dir.parent().context("Projects directory has no parent")?;
+    let path = history_path(claude_dir);
+    let old_lines = if path.exists() { read_lines(&path)? } else { Vec::new() };
+
+    let rebuilt = if rebuild { rebuild_entries(projects_dir)? } else { Vec::new() };
+    let (sessions, workspaces) = existing_sessions(projects_dir)?;
+    let reindexed = |lines: &[String]| {
+        if rebuild { merge_rebuilt(&rebuilt, lines) } else { prune(lines, &sessions, &workspaces) }
+    };
+
+    let new_lines = reindexed(&old_lines);
+    if new_lines == old_lines {
+        println!("{}", "history.jsonl is already up to date.".green());
+        return Ok(());
+    }
+
+    if dry_run {
+        if rebuild {
+            println!("Would rebuild {} with {} entries (now {})", path.display(), new_lines.len(), old_lines.len());
+        } else {
+            println!("Would remove {} stale entries from {} ({} kept)", old_lines.len() - new_lines.len(), path.display(), new_lines.len());
+        }
+        println!("{}", "Dry run, nothing was changed.".yellow());
+        return Ok(());
+    }
+
+    if rebuild {
+        let prompt = format!("Replace the {} entries of {} with {} rebuilt from the transcripts?", old_lines.len(), path.display(), new_lines.len());
+        if !confirm_deletion(prompt, yes)? {
+            println!("{}", "Cancelled.".yellow());
+            return Ok(());
+        }
+    }
+
+    // Redone on the current content, in case Claude appended to the index in the meantime
+    let mut written = 0;
+    let backup = update(&path, |lines| {
+        let new_lines = reindexed(&lines);
+        written = new_lines.len();
+        Some(new_lines)
+    })?;
+
+    if rebuild {
+        println!("{} Rebuilt {} with {} entries (was {})", "OK".green(), path.display(), written, old_lines.len());
+    } else {
+        println!("{} Removed {} stale entries from {} ({} kept)", "OK".green(), old_lines.len().saturating_sub(written), path.display(), written);
+    }
+    if let Some(backup) = backup {
+        println!("{}", format!("Backup saved to {}", backup.display()).dimmed());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{history_line as entry, temp_claude_dir};
+
+    #[test]
+    fn split_sessions_removes_every_line_of_the_given_sessions() {
+        let lines = vec![entry("a", 1), entry("b", 2), entry("a", 3), entry("c", 4)];
+        let (kept, removed) = split_sessions(lines, &HashSet::from(["a", "c"]));
+
+        assert_eq!(kept, vec![entry("b", 2)]);
+        assert_eq!(removed["a"], vec![entry("a", 1), entry("a", 3)]);
+        assert_eq!(removed["c"], vec![entry("c", 4)]);
+    }
+
+    #[test]
+    fn removed_lines_go_back_in_timestamp_order() {
+        let lines = vec![entry("a", 1), entry("b", 2), entry("a", 3), entry("b", 4), entry("a", 5)];
+        let (mut kept, removed) = split_sessions(lines.clone(), &HashSet::from(["b"]));
+
+        insert_by_timestamp(&mut kept, &removed["b"]);
+        assert_eq!(kept, lines);
+    }
+
+    #[test]
+    fn lines_without_timestamp_are_restored_last() {
+        let mut lines = vec![entry("a", 1), entry("a", 3)];
+        let undated = r#"{"display":"old","project":"/tmp/ws","sessionId":"b"}"#.to_string();
+        insert_by_timestamp(&mut lines, &[undated.clone(), entry("b", 2)]);
+
+        assert_eq!(lines, vec![entry("a", 1), entry("b", 2), entry("a", 3), undated]);
+    }
+
+    #[test]
+    fn prune_keeps_existing_sessions_and_old_entries_of_existing_projects() {
+        let sessions = HashSet::from(["kept".to_string()]);
+        let workspaces = HashSet::from([encode_project_path("/tmp/ws")]);
+        let old_entry = r#"{"display":"old","project":"/tmp/ws"}"#.to_string();
+        let old_gone = r#"{"display":"old","project":"/tmp/gone"}"#.to_string();
+        let lines = vec![entry("kept", 1), entry("gone", 2), old_entry.clone(), old_gone, "not json".to_string()];
+
+        assert_eq!(prune(&lines, &sessions, &workspaces), vec![entry("kept", 1), old_entry, "not json".to_string()]);
+    }
+
+    #[test]
+    fn rebuild_keeps_pasted_contents_of_matching_prompts() {
+        let pasted = r#"{"display":"look at [Pasted text #1 +2 lines] please","pastedContents":{"1":{"id":1,"type":"text","content":"one\ntwo"}},"timestamp":5,"project":"/tmp/ws","sessionId":"a"}"#.to_string();
+        let rebuilt = vec![
+            r#"{"display":"look at one\ntwo please","pastedContents":{},"timestamp":6,"project":"/tmp/ws","sessionId":"a"}"#.to_string(),
+            entry("b", 7),
+        ];
+
+        let lines = vec![pasted.clone()];
+        assert_eq!(merge_rebuilt(&rebuilt, &lines), vec![pasted, entry("b", 7)]);
+    }
+
+    #[test]
+    fn rebuild_skips_meta_and_command_lines() {
+        let dir = temp_claude_dir("rebuild");
+        let workspace = dir.join("projects").join("-tmp-ws");
+        fs::create_dir_all(&workspace).unwrap();
+        let user = |extra: &str, text: &str| format!(
+            r#"{{"type":"user",{}"message":{{"role":"user","content":{}}},"timestamp":"2026-01-01T00:00:00Z","cwd":"/tmp/ws"}}"#,
+            extra, serde_json::to_string(text).unwrap()
+        );
+        let transcript = [
+            user(r#""isMeta":true,"#, "Caveat: the messages below were generated by the user"),
+            user("", "<command-name>/clear</command-name>"),
+            user("", "[Request interrupted by user]"),
+            user("", "fix the tests"),
+        ].join("\n");
+        fs::write(workspace.join("abc.jsonl"), transcript).unwrap();
+
+        let lines = rebuild_entries(&dir.join("projects")).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains(r#""display":"fix the tests""#));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reindex_dry_run_leaves_the_index_untouched() {
+        let dir = temp_claude_dir("reindex-dry-run");
+        let path = history_path(&dir);
+        let content = format!("{}\n{}\n", entry("gone-1", 1), entry("gone-2", 2));
+        fs::write(&path, &content).unwrap();
+
+        reindex(&dir.join("projects"), false, true, false).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2, "no backup or temp file next to the index");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn update_starts_over_when_the_index_is_appended_to() {
+        let dir = temp_claude_dir("update-append");
+        let path = history_path(&dir);
+        fs::write(&path, format!("{}\n{}\n", entry("gone", 1), entry("kept", 2))).unwrap();
+
+        let mut calls = 0;
+        update(&path, |lines| {
+            calls += 1;
+            if calls == 1 {
+                // Claude appending a prompt after the index was read
+                let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+                std::io::Write::write_all(&mut file, format!("{}\n", entry("new", 3)).as_bytes()).unwrap();
+            }
+            Some(lines.into_iter().filter(|l| !is_session_line(l, "gone")).collect())
+        }).unwrap();
+
+        assert_eq!(calls, 2);
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n{}\n", entry("kept", 2), entry("new", 3)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_the_latest_backups_are_kept() {
+        let dir = temp_claude_dir("update-backups");
+        // Named apart from history.jsonl, which other tests back up in parallel
+        let path = dir.join("backup-pruning.jsonl");
+        let backups = || fs::read_dir(crate::backups_dir().unwrap()).unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with("-backup-pruning.jsonl"))
+            .count();
+
+        for i in 0..INDEX_BACKUPS as i64 + 3 {
+            fs::write(&path, entry("a", i) + "\n").unwrap();
+            update(&path, |mut lines| {
+                lines.push(entry("b", i));
+                Some(lines)
+            }).unwrap();
+            // Backups are named by the millisecond
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        assert_eq!(backups(), INDEX_BACKUPS);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
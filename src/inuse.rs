@@ -0,0 +1,116 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::Conversation;
+
+/// What the running `claude` processes have open or were started with
+#[derive(Debug, Default)]
+pub struct Processes {
+    /// Transcripts and session files held open
+    open_files: HashSet<PathBuf>,
+    /// Session IDs passed on the command line (`--resume`, `--session-id`)
+    sessions: HashSet<String>,
+    /// Working directories, encoded like workspace folder names
+    workspaces: HashSet<String>,
+}
+
+impl Processes {
+    /// `path` is open, or is a folder with something open inside it
+    pub fn holds(&self, path: &Path) -> bool {
+        self.open_files.iter().any(|f| f.starts_with(path))
+    }
+
+    /// Mark conversations a running session uses: held open, resumed by ID, or the most
+    /// recently written transcript of a workspace a `claude` process runs in
+    pub fn mark(&self, conversations: &mut [Conversation]) {
+        if self.open_files.is_empty() && self.sessions.is_empty() && self.workspaces.is_empty() {
+            return;
+        }
+
+        let mut newest: HashMap<&Path, usize> = HashMap::new();
+        for (i, conv) in conversations.iter().enumerate() {
+            if conv.session_id.starts_with("agent-") { continue; }
+            let folder = conv.workspace_folder.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if !self.workspaces.contains(folder) { continue; }
+            let slot = newest.entry(&conv.workspace_folder).or_insert(i);
+            if conv.modified > conversations[*slot].modified {
+                *slot = i;
+            }
+        }
+        let newest: HashSet<usize> = newest.into_values().collect();
+
+        for (i, conv) in conversations.iter_mut().enumerate() {
+            let open = self.holds(&conv.path) || conv.folder_path.as_deref().is_some_and(|f| self.holds(f));
+            if open || self.sessions.contains(&conv.session_id) || newest.contains(&i) {
+                conv.is_active = true;
+            }
+        }
+    }
+}
+
+/// The native binary is `claude`, an npm install runs `node .../@anthropic-ai/claude-code/cli.js`
+#[cfg(target_os = "linux")]
+fn is_claude(args: &[String]) -> bool {
+    args.iter().take(2).any(|arg| {
+        Path::new(arg).file_name().and_then(|n| n.to_str()) == Some("claude") || arg.contains("claude-code/")
+    })
+}
+
+/// Session IDs given as `--resume <id>`, `-r <id>`, `--session-id <id>` or their `=` forms
+#[cfg(target_os = "linux")]
+fn session_args(args: &[String]) -> impl Iterator<Item = String> + '_ {
+    args.iter().enumerate().filter_map(|(i, arg)| {
+        if let Some((flag, value)) = arg.split_once('=') {
+            return matches!(flag, "--resume" | "--session-id").then(|| value.to_string());
+        }
+        if matches!(arg.as_str(), "--resume" | "-r" | "--session-id") {
+            return args.get(i + 1).filter(|v| !v.starts_with('-')).cloned();
+        }
+        None
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn scan() -> Processes {
+    use crate::workspace::encode_project_path;
+    use std::fs;
+
+    let mut procs = Processes::default();
+    let Ok(entries) = fs::read_dir("/proc") else { return procs };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let pid = entry.path();
+        if !entry.file_name().to_str().is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit())) { continue; }
+
+        // Processes of other users or ones that just exited can't be read, skip them
+        let Ok(cmdline) = fs::read(pid.join("cmdline")) else { continue };
+        let args: Vec<String> = cmdline.split(|&b| b == 0)
+            .filter(|a| !a.is_empty())
+            .map(|a| String::from_utf8_lossy(a).to_string())
+            .collect();
+        if !is_claude(&args) { continue; }
+
+        procs.sessions.extend(session_args(&args));
+        if let Ok(cwd) = fs::read_link(pid.join("cwd")) {
+            procs.workspaces.insert(encode_project_path(&cwd.to_string_lossy()));
+        }
+        if let Ok(fds) = fs::read_dir(pid.join("fd")) {
+            procs.open_files.extend(fds.filter_map(|fd| fd.ok()).filter_map(|fd| fs::read_link(fd.path()).ok()));
+        }
+    }
+    procs
+}
+
+/// Only Linux exposes other processes' open files and arguments without extra tools, elsewhere mtime has to do
+#[cfg(not(target_os = "linux"))]
+fn scan() -> Processes {
+    Processes::default()
+}
+
+static PROCESSES: OnceLock<Processes> = OnceLock::new();
+
+/// Running `claude` processes, looked up once per run
+pub fn processes() -> &'static Processes {
+    PROCESSES.get_or_init(scan)
+}
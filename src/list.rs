@@ -0,0 +1,114 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::Conversation;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ListFormat {
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+/// One conversation as printed by `chc list`
+#[derive(Debug, Serialize)]
+struct ListRow<'a> {
+    path: &'a PathBuf,
+    session_id: &'a str,
+    workspace_path: &'a str,
+    is_empty: bool,
+    is_active: bool,
+    title: Option<&'a str>,
+    timestamp: Option<DateTime<Utc>>,
+    folder_path: Option<&'a PathBuf>,
+    size: u64,
+    message_count: usize,
+    agent_count: usize,
+    root: &'a PathBuf,
+}
+
+impl<'a> From<&'a Conversation> for ListRow<'a> {
+    fn from(conv: &'a Conversation) -> Self {
+        ListRow {
+            path: &conv.path,
+            session_id: &conv.session_id,
+            workspace_path: &conv.workspace_path,
+            is_empty: conv.is_empty,
+            is_active: conv.is_active,
+            title: conv.title.as_deref(),
+            timestamp: conv.timestamp,
+            folder_path: conv.folder_path.as_ref(),
+            size: conv.size,
+            message_count: conv.message_count,
+            agent_count: conv.agent_count,
+            root: &conv.root,
+        }
+    }
+}
+
+const CSV_HEADER: &str =
+    "path,session_id,workspace_path,is_empty,is_active,title,timestamp,folder_path,size,message_count,agent_count,root";
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row(row: &ListRow) -> String {
+    [
+        csv_field(&row.path.display().to_string()),
+        csv_field(row.session_id),
+        csv_field(row.workspace_path),
+        row.is_empty.to_string(),
+        row.is_active.to_string(),
+        csv_field(row.title.unwrap_or("")),
+        row.timestamp.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        csv_field(&row.folder_path.map(|p| p.display().to_string()).unwrap_or_default()),
+        row.size.to_string(),
+        row.message_count.to_string(),
+        row.agent_count.to_string(),
+        csv_field(&row.root.display().to_string()),
+    ]
+    .join(",")
+}
+
+fn write_rows(out: &mut impl Write, rows: &[ListRow], format: ListFormat) -> io::Result<()> {
+    match format {
+        ListFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, rows)?;
+            writeln!(out)?;
+        }
+        ListFormat::Ndjson => {
+            for row in rows {
+                serde_json::to_writer(&mut *out, row)?;
+                writeln!(out)?;
+            }
+        }
+        ListFormat::Csv => {
+            writeln!(out, "{}", CSV_HEADER)?;
+            for row in rows {
+                writeln!(out, "{}", csv_row(row))?;
+            }
+        }
+    }
+    out.flush()
+}
+
+pub fn print(conversations: &[Conversation], format: ListFormat) -> Result<()> {
+    let rows: Vec<ListRow> = conversations.iter().map(ListRow::from).collect();
+    match write_rows(&mut io::stdout().lock(), &rows, format) {
+        // Output piped into `head` and friends
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
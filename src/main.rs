@@ -1,19 +1,52 @@
+mod archive;
+mod compact;
+mod config;
+mod du;
+mod edit;
+mod export;
+mod history;
+mod inuse;
+mod list;
+mod meta;
+mod orphans;
+mod preview;
+mod report;
+mod search;
+mod secrets;
+mod sort;
+mod stats;
+#[cfg(test)]
+mod testing;
+mod trash;
+mod workspace;
+
 use anyhow::{Context, Result};
-use chrono::{DateTime, Local, Utc};
-use clap::Parser;
+use chrono::{DateTime, Duration, Local, Utc};
+use clap::{Parser, Subcommand};
 use colored::Colorize;
 use console::{Key, Term};
 use dialoguer::{theme::ColorfulTheme, Confirm};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use rayon::prelude::*;
 use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Instant, SystemTime};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Manage and clean Claude Code conversation history")]
 struct Args {
-    #[arg(short, long, help = "Filter by workspace (e.g., myproject)")]
+    #[arg(short, long, global = true, help = "Filter by workspace (e.g., myproject)")]
     workspace: Option<String>,
 
+    #[arg(long = "claude-dir", global = true, value_name = "PATH", help = "Claude data directory to scan, repeat to scan several (default: $CLAUDE_CONFIG_DIR or ~/.claude)")]
+    claude_dirs: Vec<PathBuf>,
+
     #[arg(short, long, help = "Only show empty conversations")]
     empty_only: bool,
 
@@ -23,13 +56,234 @@ struct Args {
     #[arg(long, help = "Also delete warmup agent files (use with caution)")]
     delete_warmup: bool,
 
+    #[arg(long, value_parser = parse_duration, help = "Delete conversations last active longer ago than this (e.g., 30d, 12w)")]
+    older_than: Option<Duration>,
+
+    #[arg(long, value_parser = parse_date, help = "Delete conversations last active before this date (e.g., 2026-01-01)")]
+    before: Option<DateTime<Utc>>,
+
+    #[arg(long, value_enum, default_value_t = sort::SortKey::LastActive, help = "Initial sort order of the interactive table")]
+    sort: sort::SortKey,
+
     #[arg(short, long, help = "List all workspaces")]
     list_workspaces: bool,
 
-    #[arg(long, help = "Include warmup/subagent conversations")]
+    #[arg(long, global = true, help = "Include warmup/subagent conversations")]
     include_agents: bool,
+
+    #[arg(long, global = true, help = "Show what would be deleted without deleting anything")]
+    dry_run: bool,
+
+    #[arg(short, long, global = true, help = "Delete without asking for confirmation")]
+    yes: bool,
+
+    #[arg(long, global = true, help = "Also delete or archive conversations that are in use")]
+    force: bool,
+
+    #[arg(short, long, global = true, help = "Print how long each scan phase takes")]
+    verbose: bool,
+
+    #[arg(long, global = true, help = "Parse every transcript instead of using the metadata cache")]
+    no_cache: bool,
+
+    #[arg(long, global = true, value_name = "PATH", help = "Config file to read (default: ~/.config/chc/config.toml)")]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "Manage conversations moved to the trash")]
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+
+    #[command(about = "Print all conversations in a machine-readable format")]
+    List {
+        #[arg(long, value_enum, default_value_t = list::ListFormat::Json, help = "Output format")]
+        format: list::ListFormat,
+    },
+
+    #[command(about = "Export a conversation transcript to Markdown, HTML or JSON")]
+    Export {
+        #[arg(help = "Session ID (or a unique prefix)")]
+        session: String,
+
+        #[arg(long, value_enum, default_value_t = export::ExportFormat::Md, help = "Output format")]
+        format: export::ExportFormat,
+
+        #[arg(short, long, help = "Write to this file instead of stdout")]
+        output: Option<PathBuf>,
+    },
+
+    #[command(about = "Search the messages of all conversations")]
+    Search {
+        #[arg(help = "Text to search for (a regular expression with --regex)")]
+        query: String,
+
+        #[arg(short = 'E', long, help = "Treat the query as a regular expression")]
+        regex: bool,
+
+        #[arg(short, long, help = "Case-insensitive search")]
+        ignore_case: bool,
+
+        #[arg(short = 'C', long, default_value_t = 1, help = "Lines of context around each match")]
+        context: usize,
+
+        #[arg(short, long, help = "Open the interactive selector with the matching conversations")]
+        select: bool,
+    },
+
+    #[command(about = "Show disk usage per workspace and the largest conversations")]
+    Du {
+        #[arg(long, default_value_t = 10, help = "Number of largest conversations to list")]
+        top: usize,
+    },
+
+    #[command(about = "Delete what a cleanup profile from the config file selects")]
+    Clean {
+        #[arg(long, help = "Name of a [profile.<name>] section in the config file")]
+        profile: String,
+    },
+
+    #[command(about = "Pack conversations into compressed bundles and remove the originals")]
+    Archive {
+        #[arg(help = "Session IDs (or unique prefixes) to archive")]
+        sessions: Vec<String>,
+
+        #[arg(long, value_parser = parse_duration, help = "Archive conversations last active longer ago than this (e.g., 30d, 12w)")]
+        older_than: Option<Duration>,
+
+        #[arg(long, value_parser = parse_date, help = "Archive conversations last active before this date (e.g., 2026-01-01)")]
+        before: Option<DateTime<Utc>>,
+
+        #[arg(long, value_enum, default_value_t = archive::Compression::Zst, help = "Bundle compression")]
+        compression: archive::Compression,
+
+        #[arg(long, help = "Write one bundle per workspace instead of one per run")]
+        per_workspace: bool,
+
+        #[arg(short, long, value_name = "DIR", help = "Folder to write bundles to (default: the chc data dir's archive/)")]
+        output: Option<PathBuf>,
+    },
+
+    #[command(about = "Put the conversations of an archive bundle back")]
+    Unarchive {
+        #[arg(help = "Bundle written by chc archive (.tar.zst or .tar.gz)")]
+        bundle: PathBuf,
+    },
+
+    #[command(about = "Find agent files and session folders whose conversation is gone")]
+    Orphans,
+
+    #[command(about = "Shrink transcripts by replacing large tool results and images with placeholders")]
+    Compact {
+        #[arg(long, value_name = "SIZE", default_value = "16k", value_parser = parse_size, help = "Replace tool results larger than this (e.g., 16k, 1m)")]
+        max_tool_result: u64,
+
+        #[arg(long, help = "Also replace every image with a placeholder")]
+        strip_images: bool,
+
+        #[arg(long, help = "Don't back up the transcripts, the removed content is then gone for good")]
+        no_backup: bool,
+    },
+
+    #[command(about = "Show turns, tool calls, tokens and estimated cost per workspace or for one conversation")]
+    Stats {
+        #[arg(help = "Session ID (or a unique prefix) to show on its own")]
+        session: Option<String>,
+    },
+
+    #[command(about = "Show activity over time across all workspaces")]
+    Report {
+        #[arg(long, value_enum, default_value_t = report::Period::Day, help = "Group conversations by day or week")]
+        by: report::Period,
+
+        #[arg(long, value_name = "N", default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..=3660),
+            help = "Number of days or weeks to chart, up to 3660")]
+        last: u32,
+
+        #[arg(long, value_enum, default_value_t = report::ReportFormat::Text, help = "Output format")]
+        format: report::ReportFormat,
+    },
+
+    #[command(about = "Find API keys, tokens and private keys in transcripts")]
+    Secrets {
+        #[command(subcommand)]
+        action: SecretsAction,
+    },
+
+    #[command(about = "Manage the transcript metadata cache")]
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    #[command(about = "Remove stale entries from ~/.claude/history.jsonl")]
+    Reindex {
+        #[arg(long, help = "Regenerate the whole index from the transcripts under projects/")]
+        rebuild: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TrashAction {
+    #[command(about = "List trashed conversations")]
+    List,
+
+    #[command(about = "Move a trashed conversation back to its original location")]
+    Restore {
+        #[arg(help = "Session ID (or a unique prefix)")]
+        session: String,
+    },
+
+    #[command(about = "Permanently delete trashed conversations")]
+    Empty {
+        #[arg(long, value_parser = parse_duration, help = "Only remove entries trashed before this age (e.g., 30d, 12h)")]
+        older_than: Option<Duration>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SecretsAction {
+    #[command(about = "Report each possible secret with its session, line and rule")]
+    Scan {
+        #[arg(help = "Session ID (or a unique prefix) to scan on its own")]
+        session: Option<String>,
+    },
+
+    #[command(about = "Replace found secrets with [REDACTED:<rule>], keeping a backup of each file")]
+    Redact {
+        #[arg(help = "Session ID (or a unique prefix) to redact on its own")]
+        session: Option<String>,
+
+        #[arg(long, help = "Don't back up the transcripts, the backups would still hold the secrets")]
+        no_backup: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    #[command(about = "Delete cached transcript metadata and resolved workspace paths")]
+    Clear,
+}
+
+/// Exit code when no conversation matched the batch selectors
+const EXIT_NOTHING_TO_DO: u8 = 2;
+/// Exit code when some of the selected conversations could not be deleted
+const EXIT_PARTIAL_FAILURE: u8 = 3;
+
+/// Columns of the interactive table other than TITLE: checkbox, LAST ACTIVE, SIZE, SUB, PROJECT and the gaps
+const TABLE_FIXED_WIDTH: usize = 67;
+
+/// Set from `--verbose`, read by the scanner
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+/// Set from `--force`, lets conversations that are in use be deleted
+static FORCE: AtomicBool = AtomicBool::new(false);
+
 #[derive(Debug, Clone)]
 struct Conversation {
     path: PathBuf,
@@ -40,7 +294,17 @@ struct Conversation {
     is_active: bool,
     title: Option<String>,
     timestamp: Option<DateTime<Utc>>,
+    /// Timestamp of the first entry
+    started: Option<DateTime<Utc>>,
+    modified: Option<DateTime<Utc>>,
     folder_path: Option<PathBuf>,
+    size: u64,
+    message_count: usize,
+    agent_count: usize,
+    /// Bytes of the transcript plus its session folder and legacy agent files
+    total_size: u64,
+    /// The Claude data directory (`.claude`) this conversation was found in
+    root: PathBuf,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,6 +315,10 @@ struct JsonlEntry {
     timestamp: Option<String>,
     #[serde(rename = "sessionId")]
     session_id: Option<String>,
+    cwd: Option<String>,
+    /// Set on lines Claude adds on the user's behalf, like the local command caveat
+    #[serde(rename = "isMeta", default)]
+    is_meta: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -58,15 +326,233 @@ struct Message {
     content: Option<serde_json::Value>,
 }
 
-fn get_claude_projects_dir() -> Result<PathBuf> {
-    let home = dirs::home_dir().context("Could not find home directory")?;
-    let projects_dir = home.join(".claude").join("projects");
-    if !projects_dir.exists() {
-        anyhow::bail!("Claude projects directory not found at: {}", projects_dir.display());
+/// The `projects/` folders to scan: every `--claude-dir`, else `$CLAUDE_CONFIG_DIR`, else `~/.claude`
+fn get_claude_projects_dirs(claude_dirs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let roots = if !claude_dirs.is_empty() {
+        claude_dirs.to_vec()
+    } else if let Some(dir) = std::env::var_os("CLAUDE_CONFIG_DIR").filter(|d| !d.is_empty()) {
+        vec![PathBuf::from(dir)]
+    } else {
+        let home = dirs::home_dir().context("Could not find home directory")?;
+        vec![home.join(".claude")]
+    };
+
+    let mut projects_dirs: Vec<PathBuf> = Vec::new();
+    for root in roots {
+        // Accept the projects/ folder itself as well as its parent
+        let projects_dir = if root.ends_with("projects") && !root.join("projects").is_dir() {
+            root
+        } else {
+            root.join("projects")
+        };
+        if !projects_dir.exists() {
+            anyhow::bail!("Claude projects directory not found at: {}", projects_dir.display());
+        }
+        let projects_dir = projects_dir.canonicalize().unwrap_or(projects_dir);
+        if !projects_dirs.contains(&projects_dir) {
+            projects_dirs.push(projects_dir);
+        }
+    }
+    Ok(projects_dirs)
+}
+
+/// A Claude data directory for display, with the home directory shortened to `~`
+fn display_root(root: &Path) -> String {
+    match dirs::home_dir().and_then(|home| root.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rel) if rel.as_os_str().is_empty() => "~".to_string(),
+        Some(rel) => format!("~/{}", rel.display()),
+        None => root.display().to_string(),
+    }
+}
+
+/// Directory where chc keeps its own state (trash, etc.)
+fn chc_data_dir() -> Result<PathBuf> {
+    // Trash entries and backups made by tests stay out of the real data dir
+    if cfg!(test) {
+        return Ok(std::env::temp_dir().join(format!("chc-test-data-{}", std::process::id())));
+    }
+    let data = dirs::data_local_dir().context("Could not find local data directory")?;
+    Ok(data.join("chc"))
+}
+
+/// Parse an age like `30d`, `12h`, `2w`, `45m` or `90s`
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: i64 = num.parse().map_err(|_| format!("invalid duration '{}', expected e.g. 30d", s))?;
+    let duration = match unit {
+        "s" => Duration::try_seconds(n),
+        "m" => Duration::try_minutes(n),
+        "h" => Duration::try_hours(n),
+        "d" | "" => Duration::try_days(n),
+        "w" => Duration::try_weeks(n),
+        _ => return Err(format!("invalid duration unit '{}', expected one of s, m, h, d, w", unit)),
+    };
+    duration.ok_or_else(|| format!("duration '{}' is too long", s))
+}
+
+/// Parse a size like `16k`, `1m` or `512` (bytes), units are powers of 1024
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num.parse().map_err(|_| format!("invalid size '{}', expected e.g. 16k", s))?;
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1024,
+        "m" | "mb" => 1024 * 1024,
+        "g" | "gb" => 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid size unit '{}', expected one of k, m, g", unit)),
+    };
+    n.checked_mul(multiplier).ok_or_else(|| format!("size '{}' is too large", s))
+}
+
+/// The moment `older_than` ago, an error if that is further back than a date can go
+fn older_than_cutoff(older_than: Duration) -> Result<DateTime<Utc>> {
+    Utc::now().checked_sub_signed(older_than)
+        .with_context(|| format!("older than {} days reaches back further than any date", older_than.num_days()))
+}
+
+/// Parse a date like `2026-01-01` (local midnight) or a full RFC 3339 timestamp
+fn parse_date(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Utc));
     }
-    Ok(projects_dir)
+    let date = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| format!("invalid date '{}', expected YYYY-MM-DD", s))?;
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|dt| dt.and_local_timezone(Local).earliest())
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| format!("invalid local date '{}'", s))
 }
 
+/// Format an age the way `parse_duration` reads it, in the largest unit that divides it evenly
+fn format_duration(d: Duration) -> String {
+    let secs = d.num_seconds();
+    [("w", 604_800), ("d", 86_400), ("h", 3_600), ("m", 60)].into_iter()
+        .find(|&(_, unit)| secs != 0 && secs % unit == 0)
+        .map(|(suffix, unit)| format!("{}{}", secs / unit, suffix))
+        .unwrap_or_else(|| format!("{}s", secs))
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Size of a file, or the total size of everything under a folder
+fn path_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else { return 0 };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| path_size(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+fn backups_dir() -> Result<PathBuf> {
+    Ok(chc_data_dir()?.join("backups"))
+}
+
+/// What follows the timestamp in the names of `path`'s backups. Subagent file names repeat across sessions,
+/// so a transcript is named by its path under `projects/`, `-tmp-ws+<session>+subagents+agent-a1.jsonl`.
+/// Anything else, like `history.jsonl`, by its file name.
+fn backup_name(path: &Path) -> Result<String> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).context("Invalid file name")?;
+    let Some(projects) = path.ancestors().find(|a| a.file_name().is_some_and(|n| n == "projects")) else {
+        return Ok(file_name.to_string());
+    };
+    let parts: Option<Vec<&str>> = path.strip_prefix(projects)?.iter().map(|p| p.to_str()).collect();
+    Ok(parts.context("Invalid file name")?.join("+"))
+}
+
+/// Copy `path` into the backups folder before it gets rewritten, as `<timestamp>-<backup name>` so earlier backups are kept
+fn backup_file(path: &Path) -> Result<Option<PathBuf>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let name = backup_name(path)?;
+    let dir = backups_dir()?;
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let backup = dir.join(format!("{}-{}", Utc::now().format("%Y%m%dT%H%M%S%.3f"), name));
+    fs::copy(path, &backup).with_context(|| format!("Failed to back up {}", path.display()))?;
+    Ok(Some(backup))
+}
+
+/// Delete all but the newest `keep` backups `backup_file` made of `path`
+fn prune_backups(path: &Path, keep: usize) -> Result<()> {
+    let name = backup_name(path)?;
+    let dir = backups_dir()?;
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let mut backups: Vec<PathBuf> = fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name().and_then(|n| n.to_str())
+                .and_then(|n| n.split_once('-'))
+                .is_some_and(|(_, backup)| backup == name)
+        })
+        .collect();
+    // The timestamp prefix sorts oldest first
+    backups.sort();
+    for backup in backups.iter().rev().skip(keep) {
+        fs::remove_file(backup).with_context(|| format!("Failed to remove {}", backup.display()))?;
+    }
+    Ok(())
+}
+
+/// Size and mtime of a file, to notice it being written to between reading and replacing it
+fn file_stamp(path: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// Write `contents` to a temp file next to `path`, ready to be renamed over it
+fn write_temp(path: &Path, contents: &[u8]) -> Result<PathBuf> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).context("Invalid file name")?;
+    let tmp = path.with_file_name(format!(".{}.chc-tmp", file_name));
+
+    let mut file = fs::File::create(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(tmp)
+}
+
+/// Write through a temp file in the same folder and rename it over `path`,
+/// so readers never see a half-written file
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = write_temp(path, contents)?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// `write_atomic`, unless `path` no longer has the `file_stamp` it was read with. Checked right before
+/// the rename, returns false and leaves `path` alone if something else wrote to it.
+fn write_atomic_unchanged(path: &Path, contents: &[u8], stamp: Option<(u64, SystemTime)>) -> Result<bool> {
+    let tmp = write_temp(path, contents)?;
+    if file_stamp(path) != stamp {
+        let _ = fs::remove_file(&tmp);
+        return Ok(false);
+    }
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(true)
+}
+
+/// Lossy fallback decoding of a workspace folder name, every `-` becomes `/`
 fn decode_workspace_name(name: &str) -> String {
     if name.starts_with('-') {
         name.replacen('-', "/", 1).replace('-', "/")
@@ -75,29 +561,6 @@ fn decode_workspace_name(name: &str) -> String {
     }
 }
 
-fn extract_title(content: &str) -> Option<String> {
-    for line in content.lines() {
-        if let Ok(entry) = serde_json::from_str::<JsonlEntry>(line) {
-            if entry.entry_type.as_deref() == Some("user") {
-                if let Some(msg) = entry.message {
-                    if let Some(content) = msg.content {
-                        let text = extract_text_from_content(&content);
-                        if !text.is_empty() && text != "Warmup" && !text.starts_with("<ide_") {
-                            let title: String = text.chars().take(50).collect();
-                            return Some(if text.chars().count() > 50 {
-                                format!("{}...", title)
-                            } else {
-                                title
-                            });
-                        }
-                    }
-                }
-            }
-        }
-    }
-    None
-}
-
 fn extract_text_from_content(content: &serde_json::Value) -> String {
     let raw = match content {
         serde_json::Value::String(s) => s.clone(),
@@ -128,199 +591,268 @@ fn extract_text_from_content(content: &serde_json::Value) -> String {
         .to_string()
 }
 
-fn extract_timestamp(content: &str) -> Option<DateTime<Utc>> {
-    let mut last_timestamp: Option<DateTime<Utc>> = None;
-    for line in content.lines() {
-        if let Ok(entry) = serde_json::from_str::<JsonlEntry>(line) {
-            if let Some(ts) = entry.timestamp {
-                if let Ok(dt) = ts.parse::<DateTime<Utc>>() {
-                    last_timestamp = Some(dt);
-                }
-            }
+/// Legacy `agent-*.jsonl` files in a workspace, keyed by the session they belong to
+fn legacy_agents(workspace_folder: &Path) -> HashMap<String, Vec<PathBuf>> {
+    let mut agents: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let Ok(entries) = fs::read_dir(workspace_folder) else { return agents };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("jsonl") { continue; }
+        if !path.file_stem().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("agent-")) { continue; }
+        if let Some(session_id) = read_session_id(&path) {
+            agents.entry(session_id).or_default().push(path);
         }
     }
-    last_timestamp
+    agents
 }
 
-fn is_warmup_only(content: &str) -> bool {
-    for line in content.lines() {
-        if let Ok(entry) = serde_json::from_str::<JsonlEntry>(line) {
-            if entry.entry_type.as_deref() == Some("user") {
-                if let Some(msg) = entry.message {
-                    if let Some(content) = msg.content {
-                        let text = extract_text_from_content(&content);
-                        if !text.is_empty() && text != "Warmup" && !text.starts_with("<ide_") {
-                            return false;
-                        }
-                    }
-                }
-            }
-        }
+/// Subagent transcripts stored in the new layout, `{sessionId}/subagents/agent-*.jsonl`
+fn subagent_files(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(folder.join("subagents")) else { return Vec::new() };
+    let mut files: Vec<PathBuf> = entries.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// A transcript found while walking `projects/`, before its contents are read
+struct Candidate {
+    path: PathBuf,
+    workspace_folder: PathBuf,
+    workspace_path: String,
+    root: PathBuf,
+    /// Legacy agent files that belong to this session
+    legacy: Vec<PathBuf>,
+}
+
+/// Print how long a scan phase took when `--verbose` is set
+fn log_timing(phase: &str, since: Instant) {
+    if VERBOSE.load(Ordering::Relaxed) {
+        eprintln!("{} {} in {:.1?}", "[time]".dimmed(), phase, since.elapsed());
     }
-    true
 }
 
-fn scan_conversations(projects_dir: &Path, workspace_filter: Option<&str>, include_agents: bool) -> Result<Vec<Conversation>> {
-    let mut conversations = Vec::new();
+/// Read a transcript's metadata and the sizes of everything that belongs to it
+fn read_conversation(candidate: Candidate) -> Result<Conversation> {
+    let Candidate { path, workspace_folder, workspace_path, root, legacy } = candidate;
 
-    for entry in fs::read_dir(projects_dir)? {
-        let entry = entry?;
-        let workspace_folder = entry.path();
-        if !workspace_folder.is_dir() { continue; }
+    let metadata = fs::metadata(&path)?;
+    let size = metadata.len();
+    let is_empty = size == 0;
 
-        let workspace_name = workspace_folder.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
-        let workspace_path = decode_workspace_name(&workspace_name);
+    // Check if file was modified within the active window (likely active)
+    let is_active = metadata.modified()
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .and_then(|d| Duration::from_std(d).ok())
+        .is_some_and(|d| d < config::get().active_window);
+    let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
 
-        if let Some(filter) = workspace_filter {
-            if !workspace_path.contains(filter) && !workspace_name.contains(filter) {
-                continue;
-            }
-        }
+    let session_id = path.file_stem().and_then(|n| n.to_str()).unwrap_or("").to_string();
+    let is_agent = session_id.starts_with("agent-");
+    let folder_path = workspace_folder.join(&session_id);
+    let folder_exists = folder_path.is_dir();
 
-        for file_entry in fs::read_dir(&workspace_folder)? {
-            let file_entry = file_entry?;
-            let file_path = file_entry.path();
+    let meta = if is_empty { meta::TranscriptMeta::default() } else { meta::read_cached(&path, &metadata).unwrap_or_default() };
 
-            if file_path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
-                continue;
-            }
+    let agent_count = if is_agent {
+        0
+    } else {
+        legacy.len() + subagent_files(&folder_path).len()
+    };
+    // Everything deleting this conversation would free: transcript, session folder and legacy agents
+    let total_size = size
+        + if folder_exists { path_size(&folder_path) } else { 0 }
+        + legacy.iter().map(|p| path_size(p)).sum::<u64>();
+
+    // For agent files, mark as warmup if they only contain warmup messages
+    let title = if is_agent && !is_empty && meta.is_warmup() {
+        Some("[Warmup]".to_string())
+    } else {
+        meta.title
+    };
 
-            let file_name = file_path.file_stem().and_then(|n| n.to_str()).unwrap_or("");
-            let is_agent = file_name.starts_with("agent-");
+    Ok(Conversation {
+        path,
+        session_id,
+        workspace_folder,
+        workspace_path,
+        is_empty,
+        is_active,
+        title,
+        timestamp: meta.timestamp,
+        started: meta.started,
+        modified,
+        folder_path: if folder_exists { Some(folder_path) } else { None },
+        size,
+        message_count: meta.message_count,
+        agent_count,
+        total_size,
+        root,
+    })
+}
 
-            // Skip agent files unless explicitly included
-            if is_agent && !include_agents {
-                continue;
+fn scan_conversations(projects_dirs: &[PathBuf], workspace_filter: Option<&str>, include_agents: bool) -> Result<Vec<Conversation>> {
+    let since = Instant::now();
+    let mut candidates = Vec::new();
+
+    for projects_dir in projects_dirs {
+        let root = projects_dir.parent().unwrap_or(projects_dir).to_path_buf();
+
+        for entry in fs::read_dir(projects_dir)? {
+            let entry = entry?;
+            let workspace_folder = entry.path();
+            if !workspace_folder.is_dir() { continue; }
+
+            let workspace_name = workspace_folder.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+            let workspace_path = workspace::resolve(&workspace_folder);
+
+            if let Some(filter) = workspace_filter {
+                if !workspace_path.contains(filter) && !workspace_name.contains(filter) {
+                    continue;
+                }
             }
 
-            let metadata = fs::metadata(&file_path)?;
-            let size = metadata.len();
-            let is_empty = size == 0;
+            let mut agents = legacy_agents(&workspace_folder);
 
-            // Check if file was modified in last 5 minutes (likely active)
-            let is_active = metadata.modified()
-                .ok()
-                .and_then(|t| t.elapsed().ok())
-                .map(|d| d.as_secs() < 300)
-                .unwrap_or(false);
+            for file_entry in fs::read_dir(&workspace_folder)? {
+                let file_entry = file_entry?;
+                let file_path = file_entry.path();
 
-            let session_id = file_name.to_string();
-            let folder_path = workspace_folder.join(&session_id);
-            let folder_exists = folder_path.is_dir();
+                if file_path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                    continue;
+                }
 
-            let (title, timestamp, is_warmup) = if !is_empty {
-                let content = fs::read_to_string(&file_path).unwrap_or_default();
-                let t = extract_title(&content);
-                let ts = extract_timestamp(&content);
-                let warmup = is_warmup_only(&content);
-                (t, ts, warmup)
-            } else {
-                (None, None, false)
-            };
+                let file_name = file_path.file_stem().and_then(|n| n.to_str()).unwrap_or("");
+                let is_agent = file_name.starts_with("agent-");
 
-            // For agent files, mark as warmup if they only contain warmup messages
-            let effective_title = if is_agent && is_warmup {
-                Some("[Warmup]".to_string())
-            } else {
-                title
-            };
+                // Skip agent files unless explicitly included
+                if is_agent && !include_agents {
+                    continue;
+                }
 
-            conversations.push(Conversation {
-                path: file_path,
-                session_id,
-                workspace_folder: workspace_folder.clone(),
-                workspace_path: workspace_path.clone(),
-                is_empty,
-                is_active,
-                title: effective_title,
-                timestamp,
-                folder_path: if folder_exists { Some(folder_path) } else { None },
-            });
+                let legacy = if is_agent { Vec::new() } else { agents.remove(file_name).unwrap_or_default() };
+                candidates.push(Candidate {
+                    path: file_path,
+                    workspace_folder: workspace_folder.clone(),
+                    workspace_path: workspace_path.clone(),
+                    root: root.clone(),
+                    legacy,
+                });
+            }
         }
     }
+    log_timing(&format!("found {} transcripts", candidates.len()), since);
 
-    // Sort: has title first, then no title, then empty. Within each group: by timestamp desc
-    conversations.sort_by(|a, b| {
-        // Priority: has_title > no_title > empty
-        let priority = |c: &Conversation| {
-            if c.is_empty { 2 }
-            else if c.title.is_none() || c.title.as_deref() == Some("[No title]") { 1 }
-            else { 0 }
-        };
-        let pa = priority(a);
-        let pb = priority(b);
-        if pa != pb {
-            return pa.cmp(&pb);
-        }
-        // Within same priority: by timestamp (newest first, None at end)
-        match (&b.timestamp, &a.timestamp) {
-            (Some(tb), Some(ta)) => tb.cmp(ta),
-            (Some(_), None) => std::cmp::Ordering::Less,    // b has time, a doesn't -> b first
-            (None, Some(_)) => std::cmp::Ordering::Greater, // a has time, b doesn't -> a first
-            (None, None) => a.path.cmp(&b.path),
-        }
-    });
+    let since = Instant::now();
+    meta::load_cache();
+    log_timing("load cache", since);
+
+    let since = Instant::now();
+    let mut conversations = candidates.into_par_iter()
+        .map(read_conversation)
+        .collect::<Result<Vec<_>>>()?;
+    log_timing("read metadata", since);
+
+    // Recently modified is only a hint, running `claude` processes say for sure
+    let since = Instant::now();
+    inuse::processes().mark(&mut conversations);
+    log_timing("check running sessions", since);
+
+    let since = Instant::now();
+    meta::save_cache();
+    log_timing("save cache", since);
+
+    let since = Instant::now();
+    conversations.sort_by(sort::default_cmp);
+    log_timing("sort", since);
 
     Ok(conversations)
 }
 
-fn list_workspaces(projects_dir: &Path) -> Result<()> {
+/// Find a conversation by session ID, or by a prefix that matches only one
+fn find_conversation(projects_dirs: &[PathBuf], workspace_filter: Option<&str>, session: &str) -> Result<Conversation> {
+    let conversations = scan_conversations(projects_dirs, workspace_filter, true)?;
+
+    if let Some(conv) = conversations.iter().find(|c| c.session_id == session) {
+        return Ok(conv.clone());
+    }
+
+    let mut matches = conversations.into_iter().filter(|c| c.session_id.starts_with(session));
+    match (matches.next(), matches.next()) {
+        (Some(conv), None) => Ok(conv),
+        (Some(_), Some(_)) => anyhow::bail!("'{}' matches more than one conversation, use the full session ID", session),
+        _ => anyhow::bail!("No conversation matches '{}'", session),
+    }
+}
+
+fn list_workspaces(projects_dirs: &[PathBuf]) -> Result<()> {
     println!("{}", "Available workspaces:".bold().cyan());
     println!();
 
-    let mut workspaces: Vec<(String, String, usize, usize)> = Vec::new();
+    let mut workspaces: Vec<(String, String, usize, usize, String)> = Vec::new();
+
+    for projects_dir in projects_dirs {
+        let root = display_root(projects_dir.parent().unwrap_or(projects_dir));
 
-    for entry in fs::read_dir(projects_dir)? {
-        let entry = entry?;
-        let workspace_folder = entry.path();
-        if !workspace_folder.is_dir() { continue; }
+        for entry in fs::read_dir(projects_dir)? {
+            let entry = entry?;
+            let workspace_folder = entry.path();
+            if !workspace_folder.is_dir() { continue; }
 
-        let workspace_name = workspace_folder.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
-        let workspace_path = decode_workspace_name(&workspace_name);
+            let workspace_name = workspace_folder.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+            let workspace_path = workspace::resolve(&workspace_folder);
 
-        let mut total = 0;
-        let mut agents = 0;
-        for e in fs::read_dir(&workspace_folder)?.filter_map(|e| e.ok()) {
-            if e.path().extension().and_then(|ext| ext.to_str()) == Some("jsonl") {
-                total += 1;
-                if e.path().file_stem().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("agent-")) {
-                    agents += 1;
+            let mut total = 0;
+            let mut agents = 0;
+            for e in fs::read_dir(&workspace_folder)?.filter_map(|e| e.ok()) {
+                if e.path().extension().and_then(|ext| ext.to_str()) == Some("jsonl") {
+                    total += 1;
+                    if e.path().file_stem().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("agent-")) {
+                        agents += 1;
+                    }
                 }
             }
-        }
 
-        workspaces.push((workspace_name, workspace_path, total, agents));
+            workspaces.push((workspace_name, workspace_path, total, agents, root.clone()));
+        }
     }
 
-    workspaces.sort_by(|a, b| a.1.cmp(&b.1));
+    workspaces.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.4.cmp(&b.4)));
 
-    for (name, path, total, agents) in workspaces {
+    for (name, path, total, agents, root) in workspaces {
         let main_count = total - agents;
-        println!("  {} {} ({} chats, {} agents)", "->".green(), path, main_count.to_string().yellow(), agents.to_string().dimmed());
+        let root_label = if projects_dirs.len() > 1 { format!(" [{}]", root) } else { String::new() };
+        println!("  {} {} ({} chats, {} agents){}", "->".green(), path, main_count.to_string().yellow(), agents.to_string().dimmed(), root_label.dimmed());
         println!("     {}", format!("-w {}", name).dimmed());
     }
 
     Ok(())
 }
 
-/// Delete conversation and its related agent files
-fn delete_conversation_with_agents(conv: &Conversation) -> Result<usize> {
-    let mut deleted = 1;
+/// Read the sessionId from the first line of a transcript
+fn read_session_id(path: &Path) -> Option<String> {
+    let file = fs::File::open(path).ok()?;
+    let mut first_line = String::new();
+    BufReader::new(file).read_line(&mut first_line).ok()?;
+    serde_json::from_str::<JsonlEntry>(&first_line).ok()?.session_id
+}
 
-    // Delete main file
-    fs::remove_file(&conv.path).with_context(|| format!("Failed to delete {}", conv.path.display()))?;
+/// Everything that belongs to a conversation: the transcript, its session folder,
+/// and legacy agent files (plus their folders) that reference it
+fn conversation_files(conv: &Conversation) -> Result<Vec<PathBuf>> {
+    let mut files = vec![conv.path.clone()];
 
-    // Delete associated folder
     if let Some(ref folder) = conv.folder_path {
         if folder.exists() {
-            fs::remove_dir_all(folder).with_context(|| format!("Failed to delete folder {}", folder.display()))?;
+            files.push(folder.clone());
         }
     }
 
-    // Delete related agent files (agent files that reference this session_id)
     // Agent files have sessionId field that matches the main conversation's file name
     if !conv.session_id.starts_with("agent-") {
-        // This is a main conversation, find and delete related agents
         for entry in fs::read_dir(&conv.workspace_folder)?.filter_map(|e| e.ok()) {
             let path = entry.path();
             if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
@@ -332,28 +864,98 @@ fn delete_conversation_with_agents(conv: &Conversation) -> Result<usize> {
                 continue;
             }
 
-            // Check if this agent belongs to our conversation
-            if let Ok(content) = fs::read_to_string(&path) {
-                if let Some(first_line) = content.lines().next() {
-                    if let Ok(entry) = serde_json::from_str::<JsonlEntry>(first_line) {
-                        if entry.session_id.as_deref() == Some(&conv.session_id) {
-                            // This agent belongs to our conversation
-                            if fs::remove_file(&path).is_ok() {
-                                deleted += 1;
-                                // Also delete agent folder if exists
-                                let agent_folder = conv.workspace_folder.join(name);
-                                if agent_folder.is_dir() {
-                                    let _ = fs::remove_dir_all(&agent_folder);
-                                }
-                            }
-                        }
-                    }
+            if read_session_id(&path).as_deref() == Some(&conv.session_id) {
+                let agent_folder = conv.workspace_folder.join(name);
+                files.push(path);
+                if agent_folder.is_dir() {
+                    files.push(agent_folder);
                 }
             }
         }
     }
 
-    Ok(deleted)
+    Ok(files)
+}
+
+/// Copies `backup_file` kept of the conversation's transcripts. They hold what was redacted or edited away,
+/// so they go to the trash with the conversation, and are removed when it is archived.
+fn conversation_backups(conv: &Conversation) -> Result<Vec<PathBuf>> {
+    let dir = backups_dir()?;
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let names: HashSet<String> = subagent_paths(conv)?.iter()
+        .chain([&conv.path])
+        .filter_map(|p| backup_name(p).ok())
+        .collect();
+    Ok(fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name().and_then(|n| n.to_str())
+                .and_then(|n| n.split_once('-'))
+                .is_some_and(|(_, name)| names.contains(name))
+        })
+        .collect())
+}
+
+/// Subagent transcripts of a conversation, `subagents/` first, then legacy agent files that reference it
+fn subagent_paths(conv: &Conversation) -> Result<Vec<PathBuf>> {
+    let mut paths = conv.folder_path.as_deref().map(subagent_files).unwrap_or_default();
+    paths.extend(
+        conversation_files(conv)?.into_iter()
+            .filter(|p| p != &conv.path && p.extension().and_then(|e| e.to_str()) == Some("jsonl")),
+    );
+    Ok(paths)
+}
+
+/// Subagents of a conversation, read like top-level conversations
+fn load_subagents(conv: &Conversation) -> Result<Vec<Conversation>> {
+    subagent_paths(conv)?.into_iter()
+        .map(|path| read_conversation(Candidate {
+            // The folder holding the transcript, so a subagent's own folder is looked up next to it
+            workspace_folder: path.parent().map(Path::to_path_buf).unwrap_or_else(|| conv.workspace_folder.clone()),
+            path,
+            workspace_path: conv.workspace_path.clone(),
+            root: conv.root.clone(),
+            legacy: Vec::new(),
+        }))
+        // A running session may still write to any of its subagents
+        .map(|agent| agent.map(|agent| Conversation { is_active: agent.is_active || conv.is_active, ..agent }))
+        .collect()
+}
+
+/// Re-read a transcript's metadata after it was rewritten in place. It keeps its in-use state,
+/// the fresh mtime is from the rewrite and not from a running session.
+fn refresh_conversation(conv: &mut Conversation) -> Result<()> {
+    let metadata = fs::metadata(&conv.path)?;
+    let meta = meta::read_cached(&conv.path, &metadata).unwrap_or_default();
+    let is_agent = conv.session_id.starts_with("agent-");
+
+    conv.total_size = conv.total_size - conv.size + metadata.len();
+    conv.size = metadata.len();
+    conv.is_empty = conv.size == 0;
+    conv.modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+    conv.title = if is_agent && !conv.is_empty && meta.is_warmup() { Some("[Warmup]".to_string()) } else { meta.title };
+    conv.timestamp = meta.timestamp;
+    conv.started = meta.started;
+    conv.message_count = meta.message_count;
+    Ok(())
+}
+
+/// Move conversation and its related agent files to the trash, its index entries go when `batch` is finished
+fn delete_conversation_with_agents(conv: &Conversation, batch: &mut trash::HistoryBatch) -> Result<usize> {
+    if conv.is_active && !FORCE.load(Ordering::Relaxed) {
+        anyhow::bail!("in use by a running session, pass --force to delete it anyway");
+    }
+    let mut files = conversation_files(conv)?;
+    files.extend(conversation_backups(conv)?);
+    trash::move_to_trash(conv, &files, batch)
+}
+
+/// When the conversation was last used: its last message, or the file's mtime if it has none
+fn last_active(conv: &Conversation) -> Option<DateTime<Utc>> {
+    conv.timestamp.or(conv.modified)
 }
 
 fn get_display_title(conv: &Conversation) -> String {
@@ -367,10 +969,10 @@ fn get_display_title(conv: &Conversation) -> String {
 }
 
 fn get_short_workspace(path: &str) -> String {
-    path.split('/').last().unwrap_or(path).to_string()
+    path.rsplit('/').next().unwrap_or(path).to_string()
 }
 
-fn run_interactive(conversations: Vec<Conversation>) -> Result<()> {
+fn run_interactive(conversations: Vec<Conversation>, sort_key: sort::SortKey) -> Result<()> {
     if conversations.is_empty() {
         println!("{}", "No conversations found.".yellow());
         return Ok(());
@@ -409,8 +1011,9 @@ fn run_interactive(conversations: Vec<Conversation>) -> Result<()> {
         if cleanup {
             let mut deleted = 0;
             let mut errors = 0;
+            let mut batch = trash::HistoryBatch::default();
             for conv in &to_delete {
-                match delete_conversation_with_agents(conv) {
+                match delete_conversation_with_agents(conv, &mut batch) {
                     Ok(_) => deleted += 1,
                     Err(e) => {
                         eprintln!("  {} Failed to delete {}: {}", "ERR".red(), conv.session_id, e);
@@ -418,10 +1021,11 @@ fn run_interactive(conversations: Vec<Conversation>) -> Result<()> {
                     }
                 }
             }
+            batch.finish();
             if errors > 0 {
-                println!("{} Deleted {} empty conversations ({} failed)", "WARN".yellow(), deleted, errors);
+                println!("{} Moved {} empty conversations to trash ({} failed)", "WARN".yellow(), deleted, errors);
             } else {
-                println!("{} Deleted {} empty conversations", "OK".green(), deleted);
+                println!("{} Moved {} empty conversations to trash", "OK".green(), deleted);
             }
             remaining.retain(|c| !c.is_empty);
         }
@@ -448,8 +1052,9 @@ fn run_interactive(conversations: Vec<Conversation>) -> Result<()> {
         if cleanup_warmup {
             let mut deleted = 0;
             let mut errors = 0;
+            let mut batch = trash::HistoryBatch::default();
             for conv in &to_delete {
-                match delete_conversation_with_agents(conv) {
+                match delete_conversation_with_agents(conv, &mut batch) {
                     Ok(_) => deleted += 1,
                     Err(e) => {
                         eprintln!("  {} Failed to delete {}: {}", "ERR".red(), conv.session_id, e);
@@ -457,10 +1062,11 @@ fn run_interactive(conversations: Vec<Conversation>) -> Result<()> {
                     }
                 }
             }
+            batch.finish();
             if errors > 0 {
-                println!("{} Deleted {} warmup agents ({} failed)", "WARN".yellow(), deleted, errors);
+                println!("{} Moved {} warmup agents to trash ({} failed)", "WARN".yellow(), deleted, errors);
             } else {
-                println!("{} Deleted {} warmup agents", "OK".green(), deleted);
+                println!("{} Moved {} warmup agents to trash", "OK".green(), deleted);
             }
             remaining.retain(|c| c.title.as_deref() != Some("[Warmup]"));
         }
@@ -472,10 +1078,41 @@ fn run_interactive(conversations: Vec<Conversation>) -> Result<()> {
         return Ok(());
     }
 
-    run_selection(remaining)
+    run_selection(remaining, sort_key)
 }
 
-fn run_selection(conversations: Vec<Conversation>) -> Result<()> {
+/// Indices from `order` whose conversation's title, workspace or session ID fuzzy-match `filter`
+fn filter_conversations(conversations: &[Conversation], order: &[usize], filter: &str, matcher: &SkimMatcherV2) -> Vec<usize> {
+    order.iter()
+        .copied()
+        .filter(|&i| {
+            filter.is_empty() || {
+                let c = &conversations[i];
+                let haystack = format!("{} {} {}", get_display_title(c), c.workspace_path, c.session_id);
+                matcher.fuzzy_match(&haystack, filter).is_some()
+            }
+        })
+        .collect()
+}
+
+/// A line of the interactive table
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Row {
+    Conversation(usize),
+    /// Subagent `.1` of conversation `.0`, shown while the conversation is expanded
+    Subagent(usize, usize),
+}
+
+/// Shows the cursor again however the selection screen is left, an error included
+struct ShowCursorOnDrop<'a>(&'a Term);
+
+impl Drop for ShowCursorOnDrop<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.show_cursor();
+    }
+}
+
+fn run_selection(mut conversations: Vec<Conversation>, sort_key: sort::SortKey) -> Result<()> {
     if conversations.is_empty() { return Ok(()); }
 
     let term = Term::stdout();
@@ -483,17 +1120,47 @@ fn run_selection(conversations: Vec<Conversation>) -> Result<()> {
     let mut selected: Vec<bool> = vec![false; conversations.len()];
     let mut viewport_start: usize = 0;
 
+    // Live filter: `/` starts typing, `visible` holds the indices of matching conversations
+    let matcher = SkimMatcherV2::default();
+    let mut filter = String::new();
+    let mut filtering = false;
+    // Display order, `s` cycles the sort key and `r` reverses it
+    let mut sort_key = sort_key;
+    let mut reverse = false;
+    let mut order = sort::order(&conversations, sort_key, reverse);
+    let mut visible: Vec<usize> = order.clone();
+
+    // Subagent tree: `e` expands a conversation, its subagents are loaded the first time
+    let mut subagents: HashMap<usize, Vec<Conversation>> = HashMap::new();
+    let mut expanded: HashSet<usize> = HashSet::new();
+    let mut selected_agents: HashSet<PathBuf> = HashSet::new();
+
     // Count active conversations
     let active_count = conversations.iter().filter(|c| c.is_active).count();
 
+    let table_width = config::get().table_width;
+    let title_width = table_width.saturating_sub(TABLE_FIXED_WIDTH).max(20);
+
+    // With several Claude data directories, show which one each conversation comes from
+    let roots: HashSet<&PathBuf> = conversations.iter().map(|c| &c.root).collect();
+    let root_width = if roots.len() > 1 {
+        roots.iter().map(|r| display_root(r).chars().count()).max().unwrap_or(0).min(24)
+    } else {
+        0
+    };
+
     // Clear screen and hide cursor
     let _ = term.clear_screen();
     let _ = term.hide_cursor();
+    let _cursor = ShowCursorOnDrop(&term);
 
     loop {
         // Get terminal height and calculate viewport
         let term_height = term.size().0 as usize;
-        let header_lines = if active_count > 0 { 8 } else { 7 }; // +1 for active line
+        let mut header_lines = if active_count > 0 { 8 } else { 7 }; // +1 for active line
+        if filtering || !filter.is_empty() {
+            header_lines += 1;
+        }
         let footer_lines = 3;
         let viewport_size = term_height.saturating_sub(header_lines + footer_lines).max(3);
 
@@ -508,34 +1175,79 @@ fn run_selection(conversations: Vec<Conversation>) -> Result<()> {
         let _ = term.move_cursor_to(0, 0);
         let _ = term.clear_screen();
 
-        let selected_count = selected.iter().filter(|&&s| s).count();
-        let viewport_end = std::cmp::min(viewport_start + viewport_size, conversations.len());
+        let rows: Vec<Row> = visible.iter()
+            .flat_map(|&i| {
+                let agents = if expanded.contains(&i) { subagents.get(&i).map_or(0, Vec::len) } else { 0 };
+                std::iter::once(Row::Conversation(i)).chain((0..agents).map(move |j| Row::Subagent(i, j)))
+            })
+            .collect();
+
+        let selected_count = selected.iter().filter(|&&s| s).count() + selected_agents.len();
+        let viewport_end = std::cmp::min(viewport_start + viewport_size, rows.len());
 
         println!("{}", "Claude Code Chat Manager".bold().cyan());
-        println!("Total: {} | Selected: {} | Showing: {}-{}/{}",
+        println!("Total: {} | Selected: {} | Showing: {}-{}/{} | Sort: {}",
             conversations.len(),
             selected_count.to_string().yellow(),
-            (viewport_start + 1).to_string().cyan(),
+            std::cmp::min(viewport_start + 1, viewport_end).to_string().cyan(),
             viewport_end.to_string().cyan(),
-            conversations.len()
+            rows.len(),
+            format!("{}{}", sort_key.label(), if reverse { " (reversed)" } else { "" }).cyan()
         );
         if active_count > 0 {
-            println!("{}", format!("  {} active (modified <5min, marked with *)", active_count).yellow());
+            println!("{}", format!("  {} in use (open in claude or modified <{}, marked with *)", active_count, format_duration(config::get().active_window)).yellow());
+        }
+        if filtering || !filter.is_empty() {
+            let caret = if filtering { "_" } else { "" };
+            println!("Filter: {}{} {}", filter.yellow().bold(), caret, format!("({} matches)", visible.len()).dimmed());
         }
         println!();
 
         println!(
-            "{:3} {:19} {:50} {}",
+            "{:3} {:19} {:tw$} {:>9} {:>4} {}",
             "".dimmed(),
             "LAST ACTIVE".dimmed(),
             "TITLE".dimmed(),
-            "PROJECT".dimmed()
+            "SIZE".dimmed(),
+            "SUB".dimmed(),
+            if root_width > 0 { format!("{:<w$} PROJECT", "ROOT", w = root_width) } else { "PROJECT".to_string() }.dimmed(),
+            tw = title_width
         );
-        println!("{}", "-".repeat(100).dimmed());
+        println!("{}", "-".repeat(table_width).dimmed());
 
-        for i in viewport_start..viewport_end {
+        if visible.is_empty() {
+            println!("{}", "  No conversations match the filter".dimmed());
+        }
+
+        for (row, &line) in rows.iter().enumerate().take(viewport_end).skip(viewport_start) {
+            let is_cur = row == cursor;
+
+            let i = match line {
+                Row::Conversation(i) => i,
+                Row::Subagent(i, j) => {
+                    let agent = &subagents[&i][j];
+                    let checkbox = if selected_agents.contains(&agent.path) {
+                        "[/]".green().bold().to_string()
+                    } else {
+                        "[ ]".to_string()
+                    };
+                    let time_str = agent.timestamp
+                        .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_else(|| format!("{:<19}", "---"));
+                    let title_display: String = format!("  └ {}", get_display_title(agent)).chars().take(title_width).collect();
+                    let line = format!(
+                        "{} {:<tw$} {:>9} {:>4} {}",
+                        time_str, title_display, format_size(agent.size), "", agent.session_id, tw = title_width
+                    );
+                    if is_cur {
+                        println!("{} {}", checkbox.on_bright_black(), line.white().bold());
+                    } else {
+                        println!("{} {}", checkbox.dimmed(), line.dimmed());
+                    }
+                    continue;
+                }
+            };
             let conv = &conversations[i];
-            let is_cur = i == cursor;
             let is_sel = selected[i];
 
             let checkbox = if is_sel {
@@ -546,114 +1258,240 @@ fn run_selection(conversations: Vec<Conversation>) -> Result<()> {
 
             let time_str = conv.timestamp
                 .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
-                .unwrap_or_else(|| "---".to_string());
+                .unwrap_or_else(|| format!("{:<19}", "---"));
 
             let title = get_display_title(conv);
             let active_marker = if conv.is_active { "*" } else { "" };
             let title_with_marker = format!("{}{}", active_marker, title);
-            let title_display: String = title_with_marker.chars().take(48).collect();
+            let title_display: String = title_with_marker.chars().take(title_width).collect();
+
+            let size_str = format!("{:>9}", format_size(conv.total_size));
+            let sub_str = match conv.agent_count {
+                0 => format!("{:>4}", ""),
+                n => format!("{}{:>3}", if expanded.contains(&i) { "▾" } else { "▸" }, n),
+            };
 
-            let project = get_short_workspace(&conv.workspace_path);
+            let project = if root_width > 0 {
+                let root: String = display_root(&conv.root).chars().take(root_width).collect();
+                format!("{:<w$} {}", root, get_short_workspace(&conv.workspace_path), w = root_width)
+            } else {
+                get_short_workspace(&conv.workspace_path)
+            };
 
             if is_cur {
                 if conv.is_active {
                     println!(
-                        "{} {} {} {}",
+                        "{} {} {} {} {} {}",
                         checkbox.on_bright_black(),
                         time_str.red().bold(),
-                        format!("{:<48}", title_display).red().bold(),
+                        format!("{:<tw$}", title_display, tw = title_width).red().bold(),
+                        size_str.red().bold(),
+                        sub_str.red().bold(),
                         project.cyan().bold()
                     );
                 } else {
                     println!(
-                        "{} {} {} {}",
+                        "{} {} {} {} {} {}",
                         checkbox.on_bright_black(),
                         time_str.yellow().bold(),
-                        format!("{:<48}", title_display).white().bold(),
+                        format!("{:<tw$}", title_display, tw = title_width).white().bold(),
+                        size_str.white().bold(),
+                        sub_str.white().bold(),
                         project.cyan().bold()
                     );
                 }
             } else if is_sel {
                 println!(
-                    "{} {} {} {}",
+                    "{} {} {} {} {} {}",
                     checkbox,
                     time_str.yellow(),
-                    format!("{:<48}", title_display).white(),
+                    format!("{:<tw$}", title_display, tw = title_width).white(),
+                    size_str.white(),
+                    sub_str.white(),
                     project.cyan()
                 );
             } else if conv.is_active {
                 println!(
-                    "{} {} {} {}",
+                    "{} {} {} {} {} {}",
                     checkbox.dimmed(),
                     time_str.red(),
-                    format!("{:<48}", title_display).red(),
+                    format!("{:<tw$}", title_display, tw = title_width).red(),
+                    size_str.red(),
+                    sub_str.red(),
                     project.dimmed()
                 );
             } else {
                 println!(
-                    "{} {} {} {}",
+                    "{} {} {:<tw$} {} {} {}",
                     checkbox.dimmed(),
                     time_str,
-                    format!("{:<48}", title_display),
-                    project.dimmed()
+                    title_display,
+                    size_str.dimmed(),
+                    sub_str.dimmed(),
+                    project.dimmed(),
+                    tw = title_width
                 );
             }
         }
 
         println!();
-        println!("{}", "-".repeat(100).dimmed());
+        println!("{}", "-".repeat(table_width).dimmed());
 
-        if selected_count > 0 {
+        if filtering {
+            println!(
+                "{} {} {} {}",
+                "[type]Filter".dimmed(),
+                "[↑/↓]Move".dimmed(),
+                "[Enter]Done".dimmed(),
+                "[Esc]Clear".dimmed()
+            );
+        } else if selected_count > 0 {
             println!(
                 "{} {}",
-                format!("Delete {} chat(s)?", selected_count).red().bold(),
+                format!("Delete {} item(s)?", selected_count).red().bold(),
                 "[ENTER=Delete] [ESC=Cancel]".dimmed()
             );
         } else {
             println!(
-                "{} {} {} {} {} {}",
+                "{} {} {} {} {} {} {} {} {} {}",
                 "[j/k]Move".dimmed(),
                 "[Space]Select".dimmed(),
                 "[a]All".dimmed(),
                 "[n]None".dimmed(),
+                "[/]Filter".dimmed(),
+                "[p]Preview".dimmed(),
+                "[e]Subagents".dimmed(),
+                "[s/r]Sort".dimmed(),
                 "[PgUp/PgDn]Page".dimmed(),
                 "[q]Quit".dimmed()
             );
         }
 
-        match term.read_key()? {
+        let last_row = rows.len().saturating_sub(1);
+
+        if filtering {
+            let previous = filter.clone();
+            match term.read_key()? {
+                Key::Char(c) if !c.is_control() => filter.push(c),
+                Key::Backspace => { filter.pop(); }
+                Key::Enter => filtering = false,
+                Key::Escape => {
+                    filter.clear();
+                    filtering = false;
+                }
+                Key::ArrowUp => cursor = cursor.saturating_sub(1),
+                Key::ArrowDown if cursor < last_row => cursor += 1,
+                _ => {}
+            }
+            if filter != previous {
+                visible = filter_conversations(&conversations, &order, &filter, &matcher);
+                cursor = 0;
+                viewport_start = 0;
+            }
+            continue;
+        }
+
+        let key = term.read_key()?;
+        match key {
             Key::ArrowUp | Key::Char('k') => {
-                if cursor > 0 { cursor -= 1; }
+                cursor = cursor.saturating_sub(1);
             }
-            Key::ArrowDown | Key::Char('j') => {
-                if cursor < conversations.len() - 1 { cursor += 1; }
+            Key::ArrowDown | Key::Char('j') if cursor < last_row => {
+                cursor += 1;
             }
-            Key::Char(' ') => {
-                selected[cursor] = !selected[cursor];
-                if cursor < conversations.len() - 1 { cursor += 1; }
+            Key::Char(' ') if !rows.is_empty() => {
+                match rows[cursor] {
+                    Row::Conversation(i) => selected[i] = !selected[i],
+                    Row::Subagent(i, j) => {
+                        let path = &subagents[&i][j].path;
+                        if !selected_agents.remove(path) {
+                            selected_agents.insert(path.clone());
+                        }
+                    }
+                }
+                if cursor < last_row { cursor += 1; }
             }
+            // Expand or collapse the subagents of the conversation under the cursor
+            Key::Char('e') | Key::ArrowRight | Key::ArrowLeft if !rows.is_empty() => {
+                let (i, on_parent) = match rows[cursor] {
+                    Row::Conversation(i) => (i, true),
+                    Row::Subagent(i, _) => (i, false),
+                };
+                if expanded.contains(&i) {
+                    if key != Key::ArrowRight {
+                        expanded.remove(&i);
+                        if !on_parent {
+                            cursor = rows.iter().position(|r| *r == Row::Conversation(i)).unwrap_or(0);
+                        }
+                    }
+                } else if key != Key::ArrowLeft && conversations[i].agent_count > 0 {
+                    if let std::collections::hash_map::Entry::Vacant(e) = subagents.entry(i) {
+                        e.insert(load_subagents(&conversations[i])?);
+                    }
+                    expanded.insert(i);
+                }
+            }
+            // Select/deselect only what the filter currently shows
             Key::Char('a') => {
-                for s in selected.iter_mut() { *s = true; }
+                for &i in &visible { selected[i] = true; }
             }
             Key::Char('n') => {
-                for s in selected.iter_mut() { *s = false; }
+                for &i in &visible { selected[i] = false; }
+            }
+            Key::Char('/') => {
+                filtering = true;
+            }
+            Key::Char('s') => {
+                sort_key = sort_key.next();
+                order = sort::order(&conversations, sort_key, reverse);
+                visible = filter_conversations(&conversations, &order, &filter, &matcher);
+                cursor = 0;
+                viewport_start = 0;
+            }
+            Key::Char('r') => {
+                reverse = !reverse;
+                order = sort::order(&conversations, sort_key, reverse);
+                visible = filter_conversations(&conversations, &order, &filter, &matcher);
+                cursor = 0;
+                viewport_start = 0;
+            }
+            Key::Char('p') | Key::Tab if !rows.is_empty() => {
+                let conv = match rows[cursor] {
+                    Row::Conversation(i) => &mut conversations[i],
+                    Row::Subagent(i, j) => &mut subagents.get_mut(&i).unwrap()[j],
+                };
+                preview::show(&term, conv)?;
             }
             Key::PageUp => {
                 cursor = cursor.saturating_sub(viewport_size);
             }
             Key::PageDown => {
-                cursor = std::cmp::min(cursor + viewport_size, conversations.len() - 1);
+                cursor = std::cmp::min(cursor + viewport_size, last_row);
             }
             Key::Enter => {
                 let indices: Vec<usize> = selected.iter().enumerate()
                     .filter(|&(_, s)| *s).map(|(i, _)| i).collect();
-
-                if !indices.is_empty() {
-                    // Check for active conversations
-                    let active_selected: Vec<usize> = indices.iter()
-                        .filter(|&&i| conversations[i].is_active)
-                        .copied()
-                        .collect();
+                // Subagents of a selected conversation are deleted with it anyway
+                let agents: Vec<&Conversation> = order.iter()
+                    .filter(|&&i| !selected[i])
+                    .filter_map(|i| subagents.get(i))
+                    .flatten()
+                    .filter(|a| selected_agents.contains(&a.path))
+                    .collect();
+
+                if !indices.is_empty() || !agents.is_empty() {
+                    // Conversations in use are left out unless --force was given
+                    let force = FORCE.load(Ordering::Relaxed);
+                    let in_use = indices.iter().filter(|&&i| conversations[i].is_active).count()
+                        + agents.iter().filter(|a| a.is_active).count();
+                    let (indices, agents) = if force {
+                        (indices, agents)
+                    } else {
+                        (
+                            indices.into_iter().filter(|&i| !conversations[i].is_active).collect::<Vec<_>>(),
+                            agents.into_iter().filter(|a| !a.is_active).collect::<Vec<_>>(),
+                        )
+                    };
 
                     // Direct Enter to delete - final confirmation screen
                     let _ = term.clear_screen();
@@ -661,23 +1499,45 @@ fn run_selection(conversations: Vec<Conversation>) -> Result<()> {
                     println!("{}", "Claude Code Chat Manager".bold().cyan());
                     println!();
 
-                    if !active_selected.is_empty() {
-                        println!("{}", format!("WARNING: {} conversation(s) may be currently in use!", active_selected.len()).red().bold());
-                        println!("{}", "(Modified within last 5 minutes)".red());
+                    if in_use > 0 && force {
+                        println!("{}", format!("WARNING: {} selected item(s) are in use!", in_use).red().bold());
+                        println!("{}", "(Open in a running claude session or modified recently)".red());
+                        println!();
+                    } else if in_use > 0 {
+                        println!("{}", format!("{} selected item(s) are in use and will be skipped", in_use).yellow().bold());
+                        println!("{}", "(Run with --force to delete them anyway)".yellow());
                         println!();
                     }
 
-                    println!("{} conversations to delete:", indices.len().to_string().red().bold());
-                    println!();
+                    if indices.is_empty() && agents.is_empty() {
+                        println!("Nothing left to delete. Press any key to go back...");
+                        let _ = term.read_key();
+                        continue;
+                    }
+
+                    if !indices.is_empty() {
+                        println!("{} conversations to delete:", indices.len().to_string().red().bold());
+                        println!();
 
-                    for &i in &indices {
-                        let c = &conversations[i];
-                        let active_mark = if c.is_active { " [ACTIVE]".red().to_string() } else { "".to_string() };
-                        println!("  - {}{} ({})", get_display_title(c), active_mark, c.workspace_path.dimmed());
+                        for &i in &indices {
+                            let c = &conversations[i];
+                            let active_mark = if c.is_active { " [ACTIVE]".red().to_string() } else { "".to_string() };
+                            println!("  - {}{} ({})", get_display_title(c), active_mark, c.workspace_path.dimmed());
+                        }
+                    }
+
+                    if !agents.is_empty() {
+                        if !indices.is_empty() { println!(); }
+                        println!("{} subagents to delete (parent conversations are kept):", agents.len().to_string().red().bold());
+                        println!();
+
+                        for a in &agents {
+                            println!("  - {} {} ({})", get_display_title(a), a.session_id.dimmed(), format_size(a.size));
+                        }
                     }
 
                     println!();
-                    if !active_selected.is_empty() {
+                    if in_use > 0 && force {
                         println!("{}", "Press ENTER to confirm (may cause errors in Claude Code), ESC to cancel".yellow());
                     } else {
                         println!("{}", "Press ENTER to confirm, ESC to cancel".yellow());
@@ -689,10 +1549,11 @@ fn run_selection(conversations: Vec<Conversation>) -> Result<()> {
                             Key::Enter => {
                                 let mut total_deleted = 0;
                                 let mut errors = 0;
+                                let mut batch = trash::HistoryBatch::default();
                                 println!();
                                 for &i in &indices {
                                     let conv = &conversations[i];
-                                    match delete_conversation_with_agents(conv) {
+                                    match delete_conversation_with_agents(conv, &mut batch) {
                                         Ok(n) => {
                                             total_deleted += n;
                                             println!("  {} {}", "OK".green(), get_display_title(conv).dimmed());
@@ -703,30 +1564,45 @@ fn run_selection(conversations: Vec<Conversation>) -> Result<()> {
                                         }
                                     }
                                 }
+                                for agent in &agents {
+                                    match delete_conversation_with_agents(agent, &mut batch) {
+                                        Ok(n) => {
+                                            total_deleted += n;
+                                            println!("  {} {}", "OK".green(), format!("{} {}", get_display_title(agent), agent.session_id).dimmed());
+                                        }
+                                        Err(e) => {
+                                            eprintln!("  {} {} - {}", "ERR".red(), agent.session_id, e);
+                                            errors += 1;
+                                        }
+                                    }
+                                }
+                                batch.finish();
                                 println!();
                                 if errors > 0 {
-                                    println!("{} Deleted {} files ({} failed)",
+                                    println!("{} Moved {} files to trash ({} failed)",
                                         "WARN".yellow().bold(),
                                         total_deleted.to_string().green(),
                                         errors.to_string().red()
                                     );
                                 } else {
-                                    println!("{} Deleted {} files ({} chats + related agents)",
+                                    println!("{} Moved {} files to trash ({} chats + related agents, {} subagents)",
                                         "OK".green().bold(),
                                         total_deleted.to_string().green(),
-                                        indices.len()
+                                        indices.len(),
+                                        agents.len()
                                     );
                                 }
+                                println!("{}", "Restore with: chc trash restore <session-id>".dimmed());
                                 println!();
                                 println!("Press any key to exit...");
                                 let _ = term.read_key();
                                 let _ = term.clear_screen();
-                                let _ = term.show_cursor();
                                 return Ok(());
                             }
                             Key::Escape => {
                                 // Cancel and go back
                                 for s in selected.iter_mut() { *s = false; }
+                                selected_agents.clear();
                                 break;
                             }
                             _ => {}
@@ -734,9 +1610,14 @@ fn run_selection(conversations: Vec<Conversation>) -> Result<()> {
                     }
                 }
             }
+            Key::Escape if !filter.is_empty() => {
+                filter.clear();
+                visible = order.clone();
+                cursor = 0;
+                viewport_start = 0;
+            }
             Key::Escape | Key::Char('q') => {
                 let _ = term.clear_screen();
-                let _ = term.show_cursor();
                 println!("Cancelled.");
                 return Ok(());
             }
@@ -745,95 +1626,458 @@ fn run_selection(conversations: Vec<Conversation>) -> Result<()> {
     }
 }
 
-fn main() -> Result<()> {
+/// Ask before deleting unless `--yes` was given. Without a terminal there's nobody to ask, so refuse instead.
+fn confirm_deletion(prompt: String, yes: bool) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+    if !Term::stdout().is_term() {
+        anyhow::bail!("No terminal to confirm on, pass --yes to delete without asking");
+    }
+    Ok(Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(false)
+        .interact()?)
+}
+
+/// Drop conversations that are in use unless `--force` was given, listing what was skipped.
+/// A running session keeps appending to its transcript, so moving or rewriting it underneath would lose lines.
+fn skip_in_use(conversations: &mut Vec<&Conversation>) {
+    if FORCE.load(Ordering::Relaxed) {
+        return;
+    }
+    let in_use: Vec<&Conversation> = conversations.iter().copied().filter(|c| c.is_active).collect();
+    if in_use.is_empty() {
+        return;
+    }
+
+    println!("{}", format!("Skipping {} conversations in use (pass --force to include them):", in_use.len()).yellow());
+    for conv in &in_use {
+        println!("  - {} {} ({})", get_display_title(conv), conv.session_id.dimmed(), get_short_workspace(&conv.workspace_path));
+    }
+    println!();
+    conversations.retain(|c| !c.is_active);
+}
+
+/// Print every file and folder `delete_conversation_with_agents` would move for this conversation
+fn print_deletion_plan(conv: &Conversation) -> Result<()> {
+    for path in conversation_files(conv)?.into_iter().chain(conversation_backups(conv)?) {
+        let kind = if path.is_dir() { "dir " } else { "file" };
+        println!("      {} {} ({})", kind.dimmed(), path.display(), format_size(path_size(&path)));
+    }
+    Ok(())
+}
+
+/// Remove the metadata and workspace path caches, they are rebuilt on the next scan
+fn clear_cache(dry_run: bool) -> Result<()> {
+    let mut freed = 0;
+    for path in [meta::cache_path(), workspace::cache_path()].into_iter().flatten() {
+        if path.exists() {
+            freed += path_size(&path);
+            if dry_run {
+                println!("  {} {} ({})", "file".dimmed(), path.display(), format_size(path_size(&path)));
+            } else {
+                fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+    }
+    if dry_run {
+        println!("{}", format!("Dry run, nothing was cleared ({} cached).", format_size(freed)).yellow());
+        return Ok(());
+    }
+    println!("{} Cleared cache ({})", "OK".green(), format_size(freed));
+    Ok(())
+}
+
+/// Batch deletion driven by `--delete-empty` / `--delete-warmup` / `--older-than` / `--before`, or a config profile
+fn delete_batch(projects_dirs: &[PathBuf], workspace_filter: Option<&str>, policy: &config::Profile, dry_run: bool, yes: bool) -> Result<ExitCode> {
+    println!();
+    println!("{}", "Claude Code Chat Manager".bold().cyan());
+    println!();
+
+    // Retention cutoff: both --older-than and --before must hold, so the earlier one wins
+    let cutoff = [policy.older_than.map(older_than_cutoff).transpose()?, policy.before].into_iter().flatten().min();
+
+    let conversations = scan_conversations(projects_dirs, workspace_filter, true)?;
+
+    // --older-than/--before alone select every old conversation, combined with
+    // --delete-empty/--delete-warmup they only narrow those down
+    let by_kind = policy.delete_empty || policy.delete_warmup;
+    let mut to_delete: Vec<&Conversation> = conversations.iter()
+        .filter(|c| {
+            !by_kind ||
+            (policy.delete_empty && c.is_empty) ||
+            (policy.delete_warmup && c.title.as_deref() == Some("[Warmup]"))
+        })
+        .filter(|c| policy.matches_workspace(c))
+        .filter(|c| cutoff.is_none_or(|cutoff| last_active(c).is_some_and(|t| t < cutoff)))
+        .collect();
+
+    // Legacy agent files are deleted along with their parent conversation, don't list them twice
+    let selected_sessions: HashSet<String> = to_delete.iter()
+        .filter(|c| !c.session_id.starts_with("agent-"))
+        .map(|c| c.session_id.clone())
+        .collect();
+    to_delete.retain(|c| {
+        !c.session_id.starts_with("agent-") ||
+        !read_session_id(&c.path).is_some_and(|id| selected_sessions.contains(&id))
+    });
+
+    skip_in_use(&mut to_delete);
+
+    if to_delete.is_empty() {
+        println!("{}", "No matching conversations found.".yellow());
+        return Ok(ExitCode::from(EXIT_NOTHING_TO_DO));
+    }
+
+    let empty_count = to_delete.iter().filter(|c| c.is_empty).count();
+    let warmup_count = to_delete.iter().filter(|c| c.title.as_deref() == Some("[Warmup]")).count();
+
+    // Show the list first
+    println!("Found {} conversations to delete ({} empty, {} warmup):",
+        to_delete.len().to_string().red(),
+        empty_count,
+        warmup_count
+    );
+    if let Some(cutoff) = cutoff {
+        println!("  last active before {}", cutoff.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string().yellow());
+    }
+    println!();
+    for conv in &to_delete {
+        let label = if conv.title.as_deref() == Some("[Warmup]") {
+            "[Warmup]".to_string()
+        } else {
+            get_display_title(conv)
+        };
+        let time_str = last_active(conv)
+            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "---".to_string());
+        println!("  - {} {} {} ({})",
+            time_str.dimmed(),
+            label.dimmed(),
+            conv.session_id.dimmed(),
+            get_short_workspace(&conv.workspace_path)
+        );
+        if dry_run {
+            print_deletion_plan(conv)?;
+        }
+    }
+    println!();
+
+    if dry_run {
+        println!("{}", "Dry run, nothing was deleted.".yellow());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if !confirm_deletion(format!("Delete {} conversations?", to_delete.len()), yes)? {
+        println!("{}", "Cancelled.".yellow());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut deleted = 0;
+    let mut errors = 0;
+    let mut batch = trash::HistoryBatch::default();
+    for conv in to_delete {
+        match delete_conversation_with_agents(conv, &mut batch) {
+            Ok(_) => {
+                deleted += 1;
+                println!("  {} {}", "OK".green(), conv.session_id.dimmed());
+            }
+            Err(e) => {
+                eprintln!("  {} {} - {}", "ERR".red(), conv.session_id, e);
+                errors += 1;
+            }
+        }
+    }
+    batch.finish();
+
+    if errors > 0 {
+        println!("{} Done! Moved {} to trash ({} failed)", "WARN".yellow(), deleted, errors);
+        return Ok(ExitCode::from(EXIT_PARTIAL_FAILURE));
+    }
+    println!("{}", format!("Done! Moved {} conversations to trash.", deleted).green().bold());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> Result<ExitCode> {
     let args = Args::parse();
-    let projects_dir = get_claude_projects_dir()?;
+    config::load(args.config.as_deref())?;
+    VERBOSE.store(args.verbose, Ordering::Relaxed);
+    FORCE.store(args.force, Ordering::Relaxed);
+    if args.no_cache {
+        meta::disable_cache();
+    }
 
-    if args.list_workspaces {
+    if let Some(Command::Cache { action: CacheAction::Clear }) = args.command {
         println!();
-        println!("{}", "Claude Code Chat Manager".bold().cyan());
-        println!();
-        return list_workspaces(&projects_dir);
+        clear_cache(args.dry_run)?;
+        return Ok(ExitCode::SUCCESS);
     }
 
-    if args.delete_empty || args.delete_warmup {
-        println!();
-        println!("{}", "Claude Code Chat Manager".bold().cyan());
+    if let Some(Command::Trash { action }) = args.command {
         println!();
+        match action {
+            TrashAction::List => trash::list()?,
+            TrashAction::Restore { session } => trash::restore(&session, args.dry_run)?,
+            TrashAction::Empty { older_than } => trash::empty(older_than, args.dry_run, args.yes)?,
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
 
-        let conversations = scan_conversations(&projects_dir, args.workspace.as_deref(), true)?;
+    if let Some(Command::Unarchive { ref bundle }) = args.command {
+        println!();
+        // Without --claude-dir, conversations go back where they were archived from
+        let claude_dir = if args.claude_dirs.is_empty() {
+            None
+        } else {
+            let projects_dir = get_claude_projects_dirs(&args.claude_dirs)?.remove(0);
+            projects_dir.parent().map(Path::to_path_buf)
+        };
+        archive::unarchive(bundle, claude_dir.as_deref(), args.dry_run)?;
+        return Ok(ExitCode::SUCCESS);
+    }
 
-        let to_delete: Vec<&Conversation> = conversations.iter()
-            .filter(|c| {
-                (args.delete_empty && c.is_empty) ||
-                (args.delete_warmup && c.title.as_deref() == Some("[Warmup]"))
-            })
-            .collect();
+    let projects_dirs = get_claude_projects_dirs(&args.claude_dirs)?;
 
-        if to_delete.is_empty() {
-            println!("{}", "No matching conversations found.".yellow());
-            return Ok(());
+    if let Some(Command::Reindex { rebuild }) = args.command {
+        println!();
+        for projects_dir in &projects_dirs {
+            history::reindex(projects_dir, rebuild, args.dry_run, args.yes)?;
         }
+        return Ok(ExitCode::SUCCESS);
+    }
 
-        let empty_count = to_delete.iter().filter(|c| c.is_empty).count();
-        let warmup_count = to_delete.iter().filter(|c| c.title.as_deref() == Some("[Warmup]")).count();
+    if let Some(Command::Export { ref session, format, ref output }) = args.command {
+        let conv = find_conversation(&projects_dirs, args.workspace.as_deref(), session)?;
+        export::export(&conv, format, output.as_ref())?;
+        return Ok(ExitCode::SUCCESS);
+    }
 
-        // Show the list first
-        println!("Found {} conversations to delete ({} empty, {} warmup):",
-            to_delete.len().to_string().red(),
-            empty_count,
-            warmup_count
-        );
+    if let Some(Command::Search { ref query, regex, ignore_case, context, select }) = args.command {
+        let conversations = scan_conversations(&projects_dirs, args.workspace.as_deref(), args.include_agents)?;
         println!();
-        for conv in &to_delete {
-            let label = if conv.is_empty { "[Empty]" } else { "[Warmup]" };
-            println!("  - {} {} ({})",
-                label.dimmed(),
-                conv.session_id.dimmed(),
-                get_short_workspace(&conv.workspace_path)
-            );
+        let opts = search::SearchOptions { regex, ignore_case, context };
+        let matched = search::search(conversations, query, &opts)?;
+
+        if select && !matched.is_empty() {
+            println!();
+            println!("Press any key to open the selector...");
+            let _ = Term::stdout().read_key();
+            run_selection(matched, args.sort)?;
         }
-        println!();
+        return Ok(ExitCode::SUCCESS);
+    }
 
-        // Ask for confirmation
-        let confirm = Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt(format!("Delete {} conversations?", to_delete.len()))
-            .default(false)
-            .interact()?;
+    if let Some(Command::Du { top }) = args.command {
+        let conversations = scan_conversations(&projects_dirs, args.workspace.as_deref(), false)?;
+        println!();
+        du::run(&projects_dirs, args.workspace.as_deref(), &conversations, top)?;
+        return Ok(ExitCode::SUCCESS);
+    }
 
-        if !confirm {
-            println!("{}", "Cancelled.".yellow());
-            return Ok(());
+    if let Some(Command::Clean { ref profile }) = args.command {
+        let profiles = &config::get().profiles;
+        let Some(policy) = profiles.get(profile) else {
+            let names: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            anyhow::bail!("No profile '{}' in the config file (available: {})", profile, if names.is_empty() { "none".to_string() } else { names.join(", ") });
+        };
+        if !policy.selects_anything() {
+            anyhow::bail!("Profile '{}' selects nothing, set delete_empty, delete_warmup, older_than or before", profile);
         }
+        return delete_batch(&projects_dirs, args.workspace.as_deref(), policy, args.dry_run, args.yes);
+    }
 
-        let mut deleted = 0;
-        let mut errors = 0;
-        for conv in to_delete {
-            match delete_conversation_with_agents(conv) {
-                Ok(_) => {
-                    deleted += 1;
-                    println!("  {} {}", "OK".green(), conv.session_id.dimmed());
-                }
-                Err(e) => {
-                    eprintln!("  {} {} - {}", "ERR".red(), conv.session_id, e);
-                    errors += 1;
-                }
+    if let Some(Command::Archive { ref sessions, older_than, before, compression, per_workspace, ref output }) = args.command {
+        if sessions.is_empty() && older_than.is_none() && before.is_none() {
+            anyhow::bail!("Choose what to archive: session IDs, --older-than or --before");
+        }
+        println!();
+        let conversations = scan_conversations(&projects_dirs, args.workspace.as_deref(), false)?;
+        let cutoff = [older_than.map(older_than_cutoff).transpose()?, before].into_iter().flatten().min();
+
+        // Session IDs must each name one conversation, like `export` and `trash restore`
+        let mut ids: HashSet<&str> = HashSet::new();
+        for session in sessions {
+            if let Some(conv) = conversations.iter().find(|c| &c.session_id == session) {
+                ids.insert(&conv.session_id);
+                continue;
+            }
+            let mut matches = conversations.iter().filter(|c| c.session_id.starts_with(session.as_str()));
+            match (matches.next(), matches.next()) {
+                (Some(conv), None) => { ids.insert(&conv.session_id); }
+                (Some(_), Some(_)) => anyhow::bail!("'{}' matches more than one conversation, use the full session ID", session),
+                _ => anyhow::bail!("No conversation matches '{}'", session),
             }
         }
+        let mut selected: Vec<&Conversation> = conversations.iter()
+            .filter(|c| sessions.is_empty() || ids.contains(c.session_id.as_str()))
+            .filter(|c| cutoff.is_none_or(|cutoff| last_active(c).is_some_and(|t| t < cutoff)))
+            .collect();
+        skip_in_use(&mut selected);
 
-        if errors > 0 {
-            println!("{} Done! Deleted {} ({} failed)", "WARN".yellow(), deleted, errors);
-        } else {
-            println!("{}", format!("Done! Deleted {} conversations.", deleted).green().bold());
+        let opts = archive::ArchiveOptions { compression, per_workspace, output: output.clone() };
+        return archive::run(&selected, &opts, args.dry_run, args.yes);
+    }
+
+    if let Some(Command::Compact { max_tool_result, strip_images, no_backup }) = args.command {
+        let conversations = scan_conversations(&projects_dirs, args.workspace.as_deref(), false)?;
+        let mut selected: Vec<&Conversation> = conversations.iter().collect();
+        println!();
+        skip_in_use(&mut selected);
+        let opts = compact::CompactOptions { max_tool_result, strip_images, backup: !no_backup };
+        return compact::run(&selected, &opts, args.dry_run, args.yes);
+    }
+
+    if let Some(Command::Orphans) = args.command {
+        println!();
+        return orphans::run(&projects_dirs, args.workspace.as_deref(), args.dry_run, args.yes);
+    }
+
+    if let Some(Command::Stats { ref session }) = args.command {
+        match session {
+            Some(session) => {
+                let conv = find_conversation(&projects_dirs, args.workspace.as_deref(), session)?;
+                println!();
+                stats::show(&conv)?;
+            }
+            None => {
+                let conversations = scan_conversations(&projects_dirs, args.workspace.as_deref(), false)?;
+                println!();
+                stats::report(&conversations, projects_dirs.len() > 1)?;
+            }
         }
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(Command::Secrets { ref action }) = args.command {
+        let (SecretsAction::Scan { session } | SecretsAction::Redact { session, .. }) = action;
+        let conversations = match session {
+            Some(session) => vec![find_conversation(&projects_dirs, args.workspace.as_deref(), session)?],
+            None => scan_conversations(&projects_dirs, args.workspace.as_deref(), false)?,
+        };
+        let mut selected: Vec<&Conversation> = conversations.iter().collect();
+        println!();
+        return match action {
+            SecretsAction::Scan { .. } => secrets::scan(&selected),
+            SecretsAction::Redact { no_backup, .. } => {
+                skip_in_use(&mut selected);
+                secrets::redact(&selected, !no_backup, args.dry_run, args.yes)
+            }
+        };
+    }
+
+    if let Some(Command::Report { by, last, format }) = args.command {
+        let conversations = scan_conversations(&projects_dirs, args.workspace.as_deref(), true)?;
+        report::print(&conversations, by, last, format)?;
+        return Ok(ExitCode::SUCCESS);
     }
 
-    let mut conversations = scan_conversations(&projects_dir, args.workspace.as_deref(), args.include_agents)?;
+    if let Some(Command::List { format }) = args.command {
+        let conversations = scan_conversations(&projects_dirs, args.workspace.as_deref(), args.include_agents)?;
+        list::print(&conversations, format)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if args.list_workspaces {
+        println!();
+        println!("{}", "Claude Code Chat Manager".bold().cyan());
+        println!();
+        list_workspaces(&projects_dirs)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let policy = config::Profile {
+        older_than: args.older_than,
+        before: args.before,
+        workspaces: Vec::new(),
+        delete_empty: args.delete_empty,
+        delete_warmup: args.delete_warmup,
+    };
+    if policy.selects_anything() {
+        return delete_batch(&projects_dirs, args.workspace.as_deref(), &policy, args.dry_run, args.yes);
+    }
+
+    if args.dry_run {
+        anyhow::bail!("--dry-run only applies to --delete-empty, --delete-warmup, --older-than, --before, `clean`, `archive`, `compact`, `orphans`, `secrets redact` and `trash empty`");
+    }
+
+    let mut conversations = scan_conversations(&projects_dirs, args.workspace.as_deref(), args.include_agents)?;
 
     if args.empty_only {
         conversations.retain(|c| c.is_empty);
     }
 
-    run_interactive(conversations)
+    run_interactive(conversations, args.sort)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_duration("45m"), Ok(Duration::minutes(45)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::hours(12)));
+        assert_eq!(parse_duration("30d"), Ok(Duration::days(30)));
+        assert_eq!(parse_duration(" 30 "), Ok(Duration::days(30)), "days without a unit");
+        assert_eq!(parse_duration("12w"), Ok(Duration::weeks(12)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_bad_input() {
+        assert!(parse_duration("30y").is_err());
+        assert!(parse_duration("30D").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("-5d").is_err());
+        assert!(parse_duration("999999999999999w").is_err());
+    }
+
+    #[test]
+    fn older_than_before_any_date_is_an_error() {
+        let args = Args::try_parse_from(["chc", "--older-than", "99999999w", "--dry-run"]).unwrap();
+        assert!(older_than_cutoff(args.older_than.unwrap()).is_err());
+
+        let cutoff = older_than_cutoff(parse_duration("30d").unwrap()).unwrap();
+        assert!(cutoff < Utc::now() - Duration::days(29));
+    }
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("16k"), Ok(16 * 1024));
+        assert_eq!(parse_size("2MB"), Ok(2 * 1024 * 1024));
+        assert_eq!(parse_size("1g"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("16x").is_err());
+        assert!(parse_size("k").is_err());
+    }
+
+    #[test]
+    fn parse_size_rejects_overflow() {
+        assert!(parse_size("99999999999g").is_err());
+        assert!(parse_size("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn backups_of_same_named_subagents_stay_apart() {
+        let dir = testing::temp_claude_dir("backup-names");
+        let agent = |session: &str| {
+            let transcript = testing::write_transcript(&dir, "-tmp-backup-names", session, &["hi"]);
+            let agent = transcript.with_extension("").join("subagents").join("agent-a1.jsonl");
+            fs::create_dir_all(agent.parent().unwrap()).unwrap();
+            fs::write(&agent, "{}\n").unwrap();
+            agent
+        };
+        let (first, second) = (agent("backup-names-1"), agent("backup-names-2"));
+        let backup = backup_file(&second).unwrap().unwrap();
+        assert!(backup.to_string_lossy().ends_with("-tmp-backup-names+backup-names-2+subagents+agent-a1.jsonl"));
+
+        assert!(!conversation_backups(&testing::conversation(&dir, "backup-names-1")).unwrap().contains(&backup));
+        assert_eq!(conversation_backups(&testing::conversation(&dir, "backup-names-2")).unwrap(), vec![backup.clone()]);
+        assert!(first.exists());
+        fs::remove_file(backup).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,293 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::{chc_data_dir, config, extract_text_from_content, write_atomic, JsonlEntry};
+
+/// Bytes read from the end of a transcript when looking for the last timestamp, doubled until a complete line fits
+const TAIL_CHUNK: u64 = 64 * 1024;
+/// Bumped whenever `TranscriptMeta` or the way it is read changes, older cache files are ignored
+const CACHE_VERSION: u32 = 2;
+
+/// Everything the scanner needs from a transcript, read in one pass
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TranscriptMeta {
+    /// First user message that isn't a warmup or IDE notification
+    pub title: Option<String>,
+    /// Timestamp of the first entry
+    pub started: Option<DateTime<Utc>>,
+    /// Timestamp of the last entry
+    pub timestamp: Option<DateTime<Utc>>,
+    /// User and assistant entries
+    pub message_count: usize,
+}
+
+impl TranscriptMeta {
+    /// No user message other than the configured warmup messages or IDE notifications
+    pub fn is_warmup(&self) -> bool {
+        self.title.is_none()
+    }
+}
+
+/// Just the fields needed to count messages. The message body is skipped without being built.
+#[derive(Deserialize)]
+struct EntryKind {
+    #[serde(rename = "type")]
+    entry_type: Option<String>,
+}
+
+fn parse_timestamp(ts: Option<String>) -> Option<DateTime<Utc>> {
+    ts.and_then(|ts| ts.parse().ok())
+}
+
+fn title_from(entry: JsonlEntry) -> Option<String> {
+    if entry.entry_type.as_deref() != Some("user") {
+        return None;
+    }
+    let text = extract_text_from_content(&entry.message?.content?);
+    let config = config::get();
+    if text.is_empty() || config.warmup_messages.contains(&text) || text.starts_with("<ide_") {
+        return None;
+    }
+    let title: String = text.chars().take(config.title_length).collect();
+    Some(if text.chars().count() > config.title_length { format!("{}...", title) } else { title })
+}
+
+/// Timestamp of the last entry that has one, reading backwards from the end of the file
+fn last_timestamp(file: &mut fs::File, len: u64) -> Option<DateTime<Utc>> {
+    let mut chunk = TAIL_CHUNK;
+    loop {
+        let start = len.saturating_sub(chunk);
+        file.seek(SeekFrom::Start(start)).ok()?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail).ok()?;
+
+        let mut lines: Vec<&[u8]> = tail.split(|&b| b == b'\n').collect();
+        // The first line is cut off unless the chunk starts at the beginning of the file
+        if start > 0 {
+            lines.remove(0);
+        }
+        let found = lines.iter().rev()
+            .filter_map(|line| serde_json::from_slice::<JsonlEntry>(line).ok())
+            .find_map(|entry| parse_timestamp(entry.timestamp));
+
+        if found.is_some() || start == 0 {
+            return found;
+        }
+        chunk *= 2;
+    }
+}
+
+/// Stream a transcript once. Lines are fully parsed only until the title and start time are known,
+/// after that only their type is read to count messages. The last timestamp comes from the file's tail.
+pub fn read(path: &Path) -> Result<TranscriptMeta> {
+    let mut file = fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let len = file.metadata()?.len();
+    let mut meta = TranscriptMeta::default();
+    let mut title_done = false;
+
+    // Split on bytes, so a line that isn't valid UTF-8 is decoded lossily instead of ending the read
+    for line in BufReader::new(&mut file).split(b'\n') {
+        let Ok(line) = line else { break };
+        let line = String::from_utf8_lossy(&line);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry_type = if title_done && meta.started.is_some() {
+            let Ok(kind) = serde_json::from_str::<EntryKind>(&line) else { continue };
+            kind.entry_type
+        } else {
+            let Ok(entry) = serde_json::from_str::<JsonlEntry>(&line) else { continue };
+            let entry_type = entry.entry_type.clone();
+            if meta.started.is_none() {
+                meta.started = parse_timestamp(entry.timestamp.clone());
+            }
+            if !title_done {
+                meta.title = title_from(entry);
+                title_done = meta.title.is_some();
+            }
+            entry_type
+        };
+
+        if matches!(entry_type.as_deref(), Some("user") | Some("assistant")) {
+            meta.message_count += 1;
+        }
+    }
+
+    meta.timestamp = last_timestamp(&mut file, len);
+    Ok(meta)
+}
+
+/// Metadata of a transcript as it was when it had this size and mtime
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    size: u64,
+    modified: DateTime<Utc>,
+    meta: TranscriptMeta,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cache {
+    version: u32,
+    /// Config the titles were extracted with, a cache built with other settings is ignored
+    #[serde(default)]
+    title_length: usize,
+    #[serde(default)]
+    warmup_messages: Vec<String>,
+    entries: HashMap<PathBuf, CacheEntry>,
+    /// Entries were added since the cache was loaded
+    #[serde(skip)]
+    dirty: bool,
+}
+
+/// Transcript metadata, loaded from and saved to the chc data dir
+static CACHE: Mutex<Option<Cache>> = Mutex::new(None);
+/// Cleared by `--no-cache`
+static CACHE_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn cache_path() -> Option<PathBuf> {
+    chc_data_dir().ok().map(|d| d.join("metadata.json"))
+}
+
+/// Parse every transcript from scratch and leave the cache file alone
+pub fn disable_cache() {
+    CACHE_ENABLED.store(false, Ordering::Relaxed);
+}
+
+fn with_cache<T>(f: impl FnOnce(&mut Cache) -> T) -> T {
+    let mut guard = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let config = config::get();
+    let cache = guard.get_or_insert_with(|| {
+        cache_path()
+            .and_then(|p| fs::read(p).ok())
+            .and_then(|data| serde_json::from_slice::<Cache>(&data).ok())
+            .filter(|c| c.version == CACHE_VERSION && c.title_length == config.title_length && c.warmup_messages == config.warmup_messages)
+            .unwrap_or_else(|| Cache {
+                version: CACHE_VERSION,
+                title_length: config.title_length,
+                warmup_messages: config.warmup_messages.clone(),
+                ..Cache::default()
+            })
+    });
+    f(cache)
+}
+
+/// Load the cache file now rather than on the first lookup, so its cost shows up on its own in `--verbose`
+pub fn load_cache() {
+    if CACHE_ENABLED.load(Ordering::Relaxed) {
+        with_cache(|_| ());
+    }
+}
+
+/// Like `read`, but skips parsing when the file's size and mtime match the cached entry
+pub fn read_cached(path: &Path, metadata: &fs::Metadata) -> Result<TranscriptMeta> {
+    let Some(modified) = metadata.modified().ok().map(DateTime::<Utc>::from) else { return read(path) };
+    if !CACHE_ENABLED.load(Ordering::Relaxed) {
+        return read(path);
+    }
+
+    let size = metadata.len();
+    let hit = with_cache(|cache| {
+        cache.entries.get(path)
+            .filter(|e| e.size == size && e.modified == modified)
+            .map(|e| e.meta.clone())
+    });
+    if let Some(meta) = hit {
+        return Ok(meta);
+    }
+
+    let meta = read(path)?;
+    with_cache(|cache| {
+        cache.entries.insert(path.to_path_buf(), CacheEntry { size, modified, meta: meta.clone() });
+        cache.dirty = true;
+    });
+    Ok(meta)
+}
+
+/// Write the cache back if anything changed, dropping entries of files that are gone
+pub fn save_cache() {
+    if !CACHE_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    with_cache(|cache| {
+        let before = cache.entries.len();
+        cache.entries.retain(|path, _| path.exists());
+        if !cache.dirty && cache.entries.len() == before {
+            return;
+        }
+
+        let Some(path) = cache_path() else { return };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_vec(cache) {
+            if write_atomic(&path, &json).is_ok() {
+                cache.dirty = false;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_claude_dir;
+
+    fn entry(ts: &str) -> String {
+        format!(r#"{{"type":"user","message":{{"role":"user","content":"hi"}},"timestamp":"{}"}}"#, ts)
+    }
+
+    /// A line without a timestamp, `len` bytes long
+    fn filler(len: usize) -> String {
+        let line = r#"{"type":"summary","summary":""}"#;
+        line.replace(r#""summary":""#, &format!(r#""summary":"{}"#, "x".repeat(len - line.len())))
+    }
+
+    fn last_timestamp_of(name: &str, content: &str) -> Option<DateTime<Utc>> {
+        let dir = temp_claude_dir(name);
+        let path = dir.join("t.jsonl");
+        fs::write(&path, content).unwrap();
+        let mut file = fs::File::open(&path).unwrap();
+        let found = last_timestamp(&mut file, content.len() as u64);
+        fs::remove_dir_all(&dir).unwrap();
+        found
+    }
+
+    #[test]
+    fn last_timestamp_doubles_the_chunk_until_it_finds_one() {
+        let chunk = TAIL_CHUNK as usize;
+        let content = [filler(3 * chunk), entry("2026-01-02T00:00:00Z"), filler(chunk + chunk / 2)].join("\n");
+        assert_eq!(last_timestamp_of("meta-doubling", &content), parse_timestamp(Some("2026-01-02T00:00:00Z".into())));
+    }
+
+    #[test]
+    fn last_timestamp_keeps_the_first_line_of_a_small_file() {
+        let content = format!("{}\n{}\n", entry("2026-01-01T00:00:00Z"), filler(100));
+        assert!((content.len() as u64) < TAIL_CHUNK);
+        assert_eq!(last_timestamp_of("meta-small", &content), parse_timestamp(Some("2026-01-01T00:00:00Z".into())));
+        assert_eq!(last_timestamp_of("meta-no-timestamp", &filler(100)), None);
+    }
+
+    #[test]
+    fn read_goes_on_past_invalid_utf8() {
+        let dir = temp_claude_dir("meta-invalid-utf8");
+        let path = dir.join("t.jsonl");
+        let mut content = format!("{}\n", entry("2026-01-01T00:00:00Z")).into_bytes();
+        content.extend_from_slice(b"{\"type\":\"assistant\",\"message\":{\"content\":\"caf\xe9\"}}\n");
+        content.extend_from_slice(format!("{}\n", entry("2026-01-03T00:00:00Z")).as_bytes());
+        fs::write(&path, content).unwrap();
+
+        let meta = read(&path).unwrap();
+        assert_eq!(meta.message_count, 3);
+        assert_eq!(meta.started, parse_timestamp(Some("2026-01-01T00:00:00Z".into())));
+        assert_eq!(meta.timestamp, parse_timestamp(Some("2026-01-03T00:00:00Z".into())));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
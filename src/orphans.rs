@@ -0,0 +1,237 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::{
+    config, confirm_deletion, format_size, get_short_workspace, inuse, path_size, read_session_id, trash, workspace,
+    EXIT_NOTHING_TO_DO, EXIT_PARTIAL_FAILURE,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    /// Legacy `agent-*.jsonl` whose `sessionId` matches no main transcript in its workspace
+    Agent,
+    /// `{sessionId}/` or `agent-*/` folder whose `.jsonl` is gone
+    Folder,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Agent => "agent ",
+            Kind::Folder => "folder",
+        }
+    }
+}
+
+/// Leftovers that `delete_conversation_with_agents` can no longer reach through a parent conversation
+#[derive(Debug)]
+struct Orphan {
+    kind: Kind,
+    /// File or folder name without extension, the trash entry is restorable under it
+    name: String,
+    /// Session the agent file points to, if it has one
+    parent: Option<String>,
+    workspace_path: String,
+    root: PathBuf,
+    /// The orphan itself plus, for agents, their own `agent-*/` folder
+    files: Vec<PathBuf>,
+    size: u64,
+}
+
+impl Orphan {
+    fn title(&self) -> String {
+        match self.kind {
+            Kind::Agent => "[Orphaned agent]".to_string(),
+            Kind::Folder => "[Orphaned session folder]".to_string(),
+        }
+    }
+}
+
+/// `8-4-4-4-12` hex digits, the shape of the session IDs Claude Code generates
+fn is_session_id(name: &str) -> bool {
+    let groups: Vec<&str> = name.split('-').collect();
+    groups.len() == 5
+        && groups.iter().zip([8, 4, 4, 4, 12]).all(|(g, len)| g.len() == len && g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Held open by a running `claude` process or modified within the active window, so it may still be written to
+fn in_use(path: &Path) -> bool {
+    inuse::processes().holds(path) || fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .and_then(|d| chrono::Duration::from_std(d).ok())
+        .is_some_and(|d| d < config::get().active_window)
+}
+
+fn workspace_orphans(workspace_folder: &Path, workspace_path: &str, root: &Path) -> Result<Vec<Orphan>> {
+    // Stems of every transcript, and of the main ones only
+    let mut transcripts: HashSet<String> = HashSet::new();
+    let mut sessions: HashSet<String> = HashSet::new();
+    let mut agents: Vec<PathBuf> = Vec::new();
+    let mut folders: Vec<PathBuf> = Vec::new();
+
+    for entry in fs::read_dir(workspace_folder)?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let Some(name) = path.file_stem().and_then(|n| n.to_str()).map(str::to_string) else { continue };
+        if path.is_dir() {
+            folders.push(path);
+        } else if path.extension().and_then(|e| e.to_str()) == Some("jsonl") {
+            if name.starts_with("agent-") {
+                agents.push(path);
+            } else {
+                sessions.insert(name.clone());
+            }
+            transcripts.insert(name);
+        }
+    }
+
+    let mut orphans = Vec::new();
+
+    for path in agents {
+        if in_use(&path) { continue; }
+        // Same linkage as `conversation_files`: the agent's first line names its parent session
+        let parent = read_session_id(&path);
+        if parent.as_ref().is_some_and(|id| sessions.contains(id)) { continue; }
+
+        let name = path.file_stem().and_then(|n| n.to_str()).unwrap_or("").to_string();
+        let mut files = vec![path];
+        let folder = workspace_folder.join(&name);
+        if folder.is_dir() {
+            files.push(folder);
+        }
+        orphans.push(Orphan {
+            kind: Kind::Agent,
+            name,
+            parent,
+            workspace_path: workspace_path.to_string(),
+            root: root.to_path_buf(),
+            size: files.iter().map(|p| path_size(p)).sum(),
+            files,
+        });
+    }
+
+    for path in folders {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+        // Anything not named like a session or agent isn't ours to judge
+        let ours = is_session_id(&name) || name.starts_with("agent-");
+        if !ours || transcripts.contains(&name) || in_use(&path) { continue; }
+
+        orphans.push(Orphan {
+            kind: Kind::Folder,
+            name,
+            parent: None,
+            workspace_path: workspace_path.to_string(),
+            root: root.to_path_buf(),
+            size: path_size(&path),
+            files: vec![path],
+        });
+    }
+
+    Ok(orphans)
+}
+
+fn find(projects_dirs: &[PathBuf], workspace_filter: Option<&str>) -> Result<Vec<Orphan>> {
+    let mut orphans = Vec::new();
+
+    for projects_dir in projects_dirs {
+        let root = projects_dir.parent().unwrap_or(projects_dir);
+
+        for entry in fs::read_dir(projects_dir)?.filter_map(|e| e.ok()) {
+            let workspace_folder = entry.path();
+            if !workspace_folder.is_dir() { continue; }
+
+            let workspace_name = workspace_folder.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+            let workspace_path = workspace::resolve(&workspace_folder);
+            if let Some(filter) = workspace_filter {
+                if !workspace_path.contains(filter) && !workspace_name.contains(filter) {
+                    continue;
+                }
+            }
+
+            orphans.extend(workspace_orphans(&workspace_folder, &workspace_path, root)?);
+        }
+    }
+
+    orphans.sort_by(|a, b| a.workspace_path.cmp(&b.workspace_path).then_with(|| b.size.cmp(&a.size)));
+    Ok(orphans)
+}
+
+/// List orphaned agent files and session folders, then move them to the trash after confirmation
+pub fn run(projects_dirs: &[PathBuf], workspace_filter: Option<&str>, dry_run: bool, yes: bool) -> Result<ExitCode> {
+    let orphans = find(projects_dirs, workspace_filter)?;
+
+    if orphans.is_empty() {
+        println!("{}", "No orphaned files found.".yellow());
+        return Ok(ExitCode::from(EXIT_NOTHING_TO_DO));
+    }
+
+    let agents = orphans.iter().filter(|o| o.kind == Kind::Agent).count();
+    let total_size: u64 = orphans.iter().map(|o| o.size).sum();
+    println!("Found {} orphans ({} agent files, {} session folders, {}):",
+        orphans.len().to_string().red(),
+        agents,
+        orphans.len() - agents,
+        format_size(total_size)
+    );
+    println!();
+
+    for orphan in &orphans {
+        let parent = match (orphan.kind, &orphan.parent) {
+            (Kind::Agent, Some(parent)) => format!("parent {} is gone", parent),
+            (Kind::Agent, None) => "no sessionId".to_string(),
+            (Kind::Folder, _) => "transcript is gone".to_string(),
+        };
+        println!("  - {} {:>10} {} ({}) {}",
+            orphan.kind.label().dimmed(),
+            format_size(orphan.size),
+            orphan.name,
+            get_short_workspace(&orphan.workspace_path),
+            parent.dimmed()
+        );
+        if dry_run {
+            for path in &orphan.files {
+                let kind = if path.is_dir() { "dir " } else { "file" };
+                println!("      {} {} ({})", kind.dimmed(), path.display(), format_size(path_size(path)));
+            }
+        }
+    }
+    println!();
+
+    if dry_run {
+        println!("{}", "Dry run, nothing was deleted.".yellow());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if !confirm_deletion(format!("Delete {} orphans ({})?", orphans.len(), format_size(total_size)), yes)? {
+        println!("{}", "Cancelled.".yellow());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut deleted = 0;
+    let mut errors = 0;
+    for orphan in &orphans {
+        match trash::move_orphan(&orphan.name, &orphan.workspace_path, orphan.title(), &orphan.root, &orphan.files) {
+            Ok(_) => {
+                deleted += 1;
+                println!("  {} {}", "OK".green(), orphan.name.dimmed());
+            }
+            Err(e) => {
+                eprintln!("  {} {} - {}", "ERR".red(), orphan.name, e);
+                errors += 1;
+            }
+        }
+    }
+
+    if errors > 0 {
+        println!("{} Done! Moved {} orphans to trash ({} failed)", "WARN".yellow(), deleted, errors);
+        return Ok(ExitCode::from(EXIT_PARTIAL_FAILURE));
+    }
+    println!("{}", format!("Done! Moved {} orphans to trash.", deleted).green().bold());
+    println!("{}", "Restore with: chc trash restore <name>".dimmed());
+    Ok(ExitCode::SUCCESS)
+}
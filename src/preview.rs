@@ -0,0 +1,146 @@
+use anyhow::Result;
+use colored::Colorize;
+use console::{Key, Term};
+use std::sync::atomic::Ordering;
+
+use crate::export::{format_time, read_turns, role_label, Block, TurnPage};
+use crate::{config, edit, format_size, get_display_title, refresh_conversation, Conversation, FORCE};
+
+/// Turns rendered in the preview, long transcripts are cut after this many
+const PREVIEW_TURNS: usize = 50;
+/// Lines of a tool result shown before it's collapsed
+const TOOL_RESULT_LINES: usize = 5;
+
+/// Split a line into chunks of at most `width` characters
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars.chunks(width.max(1)).map(|c| c.iter().collect()).collect()
+}
+
+fn push_wrapped(out: &mut Vec<String>, text: &str, indent: &str, width: usize) {
+    for line in text.lines() {
+        for chunk in wrap(&line.replace('\t', "    "), width.saturating_sub(indent.len())) {
+            out.push(format!("{}{}", indent, chunk));
+        }
+    }
+}
+
+/// Render the header and the first turns of a conversation into plain lines
+fn render(conv: &Conversation, width: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    let page = read_turns(&conv.path, PREVIEW_TURNS)
+        .unwrap_or(TurnPage { turns: Vec::new(), more: false, entries: 0 });
+
+    out.push(get_display_title(conv).bold().to_string());
+    out.push(format!("{} {}", "Session:".dimmed(), conv.session_id));
+    out.push(format!("{} {}", "Project:".dimmed(), conv.workspace_path));
+    out.push(format!(
+        "{} {} | {} {} | {} {} | {} {}",
+        "Size:".dimmed(), format_size(conv.size),
+        "Entries:".dimmed(), page.entries,
+        "Messages:".dimmed(), conv.message_count,
+        "Subagents:".dimmed(), conv.agent_count
+    ));
+    out.push(String::new());
+
+    if conv.is_empty || page.turns.is_empty() {
+        out.push("(no messages)".dimmed().to_string());
+        return out;
+    }
+
+    for turn in &page.turns {
+        let label = format!("── {} · {} (L{}) ", role_label(&turn.role), format_time(turn.timestamp), turn.line);
+        let header = if turn.role == "user" { label.cyan().bold() } else { label.green().bold() };
+        out.push(header.to_string());
+
+        for block in &turn.blocks {
+            match block {
+                Block::Text { text } => push_wrapped(&mut out, text, "  ", width),
+                Block::Thinking { text } => {
+                    out.push(format!("  {}", "[thinking]".dimmed()));
+                    push_wrapped(&mut out, text, "    ", width);
+                }
+                Block::ToolUse { name, input } => {
+                    let input = input.to_string();
+                    let input: String = input.chars().take(width.saturating_sub(name.len() + 12)).collect();
+                    out.push(format!("  {} {} {}", "[tool]".yellow(), name.yellow(), input.dimmed()));
+                }
+                Block::ToolResult { content, is_error } => {
+                    let label = if *is_error { "[error]".red() } else { "[result]".yellow() };
+                    let total = content.lines().count();
+                    out.push(format!("  {} {}", label, format!("{} lines", total).dimmed()));
+                    let shown: String = content.lines().take(TOOL_RESULT_LINES).collect::<Vec<_>>().join("\n");
+                    push_wrapped(&mut out, &shown, "    ", width);
+                    if total > TOOL_RESULT_LINES {
+                        out.push(format!("    {}", format!("... {} more lines", total - TOOL_RESULT_LINES).dimmed()));
+                    }
+                }
+                Block::Image => out.push(format!("  {}", "[image]".dimmed())),
+            }
+        }
+        out.push(String::new());
+    }
+
+    if page.more {
+        out.push(format!("... only the first {} turns are shown, use `chc export {}` for the full transcript", PREVIEW_TURNS, conv.session_id).dimmed().to_string());
+    }
+
+    out
+}
+
+/// Scrollable preview of a conversation, `e` opens the message editor. Returns when the user closes it.
+pub fn show(term: &Term, conv: &mut Conversation) -> Result<()> {
+    let mut lines = render(conv, term.size().1 as usize);
+    let mut top: usize = 0;
+    let mut status: Option<String> = None;
+
+    loop {
+        let page = (term.size().0 as usize).saturating_sub(3).max(1);
+        let max_top = lines.len().saturating_sub(page);
+        top = top.min(max_top);
+
+        let _ = term.move_cursor_to(0, 0);
+        let _ = term.clear_screen();
+
+        for line in lines.iter().skip(top).take(page) {
+            println!("{}", line);
+        }
+        println!("{}", "-".repeat(config::get().table_width).dimmed());
+        if let Some(msg) = status.take() {
+            println!("{}", msg.yellow());
+        } else {
+            println!(
+                "{} {} {} {} {}",
+                format!("Lines {}-{}/{}", top + 1, (top + page).min(lines.len()), lines.len()).dimmed(),
+                "[j/k]Scroll".dimmed(),
+                "[PgUp/PgDn]Page".dimmed(),
+                "[e]Edit messages".dimmed(),
+                "[p/Tab/q]Back".dimmed()
+            );
+        }
+
+        match term.read_key()? {
+            Key::ArrowUp | Key::Char('k') => top = top.saturating_sub(1),
+            Key::ArrowDown | Key::Char('j') => top = (top + 1).min(max_top),
+            Key::PageUp => top = top.saturating_sub(page),
+            Key::PageDown | Key::Char(' ') => top = (top + page).min(max_top),
+            Key::Home | Key::Char('g') => top = 0,
+            Key::End | Key::Char('G') => top = max_top,
+            Key::Char('e') if conv.is_active && !FORCE.load(Ordering::Relaxed) => {
+                status = Some("In use by a running session, pass --force to edit it anyway".to_string());
+            }
+            Key::Char('e') if !conv.is_empty => {
+                if let Some(backup) = edit::run(term, conv)? {
+                    refresh_conversation(conv)?;
+                    lines = render(conv, term.size().1 as usize);
+                    status = Some(format!("Transcript rewritten, the old one was kept as {}", backup.display()));
+                }
+            }
+            Key::Char('p') | Key::Char('q') | Key::Tab | Key::Escape => return Ok(()),
+            _ => {}
+        }
+    }
+}
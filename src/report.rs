@@ -0,0 +1,231 @@
+use anyhow::Result;
+use chrono::{Datelike, Duration, Local, NaiveDate, Timelike};
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{last_active, Conversation};
+
+/// Workspaces listed under "most active"
+const TOP_WORKSPACES: usize = 10;
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    /// Tables and sparklines for the terminal
+    Text,
+    /// A single JSON object
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Period {
+    Day,
+    Week,
+}
+
+impl Period {
+    /// First day of the period `date` falls in, weeks start on Monday
+    fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        }
+    }
+
+    fn step(self) -> Duration {
+        match self {
+            Period::Day => Duration::days(1),
+            Period::Week => Duration::weeks(1),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Period::Day => "day",
+            Period::Week => "week",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Bucket {
+    start: NaiveDate,
+    conversations: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct WorkspaceActivity {
+    workspace_path: String,
+    conversations: usize,
+    messages: usize,
+    last_active: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    /// Main transcripts, including empty ones
+    conversations: usize,
+    workspaces: usize,
+    empty: usize,
+    /// Agent transcripts at the workspace level
+    agents: usize,
+    warmup: usize,
+    period: &'static str,
+    /// Conversations started per period, oldest first
+    activity: Vec<Bucket>,
+    most_active_workspaces: Vec<WorkspaceActivity>,
+    /// Conversations started in each hour of the day, local time
+    hours: [usize; 24],
+    /// Seconds from the first to the last message, over conversations that have both
+    average_session_secs: Option<i64>,
+    median_session_secs: Option<i64>,
+}
+
+fn build(conversations: &[Conversation], period: Period, last: u32) -> Report {
+    let (agents, mains): (Vec<&Conversation>, Vec<&Conversation>) =
+        conversations.iter().partition(|c| c.session_id.starts_with("agent-"));
+    let warmup = agents.iter().filter(|c| c.title.as_deref() == Some("[Warmup]")).count();
+    let empty = mains.iter().filter(|c| c.is_empty).count();
+
+    // When a conversation started: its first message, else whatever time we have
+    let started = |c: &Conversation| c.started.or_else(|| last_active(c)).map(|t| t.with_timezone(&Local));
+
+    let mut per_bucket: HashMap<NaiveDate, usize> = HashMap::new();
+    let mut hours = [0; 24];
+    for conv in mains.iter().filter(|c| !c.is_empty) {
+        let Some(t) = started(conv) else { continue };
+        *per_bucket.entry(period.start(t.date_naive())).or_default() += 1;
+        hours[t.hour() as usize] += 1;
+    }
+
+    let current = period.start(Local::now().date_naive());
+    let activity = (0..last as i32).rev()
+        .map(|i| current - period.step() * i)
+        .map(|start| Bucket { start, conversations: per_bucket.get(&start).copied().unwrap_or(0) })
+        .collect();
+
+    let mut workspaces: BTreeMap<&str, WorkspaceActivity> = BTreeMap::new();
+    for conv in &mains {
+        let entry = workspaces.entry(conv.workspace_path.as_str()).or_insert_with(|| WorkspaceActivity {
+            workspace_path: conv.workspace_path.clone(),
+            conversations: 0,
+            messages: 0,
+            last_active: None,
+        });
+        entry.conversations += 1;
+        entry.messages += conv.message_count;
+        let active = last_active(conv).map(|t| t.with_timezone(&Local).date_naive());
+        entry.last_active = entry.last_active.max(active);
+    }
+    let workspace_count = workspaces.len();
+    let mut most_active: Vec<WorkspaceActivity> = workspaces.into_values().collect();
+    most_active.sort_by(|a, b| b.conversations.cmp(&a.conversations).then_with(|| b.messages.cmp(&a.messages)));
+    most_active.truncate(TOP_WORKSPACES);
+
+    let mut lengths: Vec<i64> = mains.iter()
+        .filter_map(|c| Some((c.timestamp? - c.started?).num_seconds()))
+        .filter(|&secs| secs >= 0)
+        .collect();
+    lengths.sort_unstable();
+    let average_session_secs = (!lengths.is_empty()).then(|| lengths.iter().sum::<i64>() / lengths.len() as i64);
+    let median_session_secs = lengths.get(lengths.len() / 2).copied();
+
+    Report {
+        conversations: mains.len(),
+        workspaces: workspace_count,
+        empty,
+        agents: agents.len(),
+        warmup,
+        period: period.label(),
+        activity,
+        most_active_workspaces: most_active,
+        hours,
+        average_session_secs,
+        median_session_secs,
+    }
+}
+
+fn sparkline(values: &[usize]) -> String {
+    let max = values.iter().copied().max().unwrap_or(0);
+    values.iter()
+        .map(|&v| if v == 0 { ' ' } else { SPARKS[((v - 1) * SPARKS.len() / max).min(SPARKS.len() - 1)] })
+        .collect()
+}
+
+/// `3725` -> `1h 2m`
+fn format_length(secs: i64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn percent(part: usize, whole: usize) -> String {
+    if whole == 0 { "-".to_string() } else { format!("{:.1}%", part as f64 * 100.0 / whole as f64) }
+}
+
+fn print_text(report: &Report) {
+    println!("{}", "Activity report".bold().cyan());
+    println!("{} conversations in {} workspaces", report.conversations.to_string().yellow(), report.workspaces);
+    println!();
+
+    let counts: Vec<usize> = report.activity.iter().map(|b| b.conversations).collect();
+    let total: usize = counts.iter().sum();
+    println!("{}", format!("Conversations per {} (last {}):", report.period, counts.len()).bold().cyan());
+    println!("  {}", sparkline(&counts).green());
+    if let (Some(first), Some(last)) = (report.activity.first(), report.activity.last()) {
+        // Dates under both ends of the sparkline, or side by side when it is too short for that
+        let axis = if counts.len() > 21 {
+            format!("{:<w$}{}", first.start.to_string(), last.start, w = counts.len() - 10)
+        } else {
+            format!("{} .. {}", first.start, last.start)
+        };
+        println!("  {}", axis.dimmed());
+    }
+    println!("  {} total, max {} per {}", total, counts.iter().max().unwrap_or(&0), report.period);
+    println!();
+
+    println!("{}", "Busiest hours:".bold().cyan());
+    println!("  {}", sparkline(&report.hours).green());
+    println!("  {}", "0     6     12    18   23".dimmed());
+    if let Some((hour, n)) = report.hours.iter().enumerate().max_by_key(|&(_, n)| *n).filter(|&(_, n)| *n > 0) {
+        println!("  peak {:02}:00-{:02}:59 with {} conversations", hour, hour, n);
+    }
+    println!();
+
+    println!("{}", "Most active workspaces:".bold().cyan());
+    println!("  {:>6} {:>9} {:>11}  {}", "CHATS".dimmed(), "MESSAGES".dimmed(), "LAST ACTIVE".dimmed(), "WORKSPACE".dimmed());
+    for ws in &report.most_active_workspaces {
+        let last = ws.last_active.map(|d| d.to_string()).unwrap_or_else(|| "---".to_string());
+        println!("  {:>6} {:>9} {:>11}  {}", ws.conversations, ws.messages, last, ws.workspace_path);
+    }
+    println!();
+
+    println!("{}", "Sessions:".bold().cyan());
+    match (report.average_session_secs, report.median_session_secs) {
+        (Some(avg), Some(median)) => println!("  length {} on average, {} median (first to last message)", format_length(avg), format_length(median)),
+        _ => println!("  {}", "no timestamps to measure session length".dimmed()),
+    }
+    println!("  empty   {:>5} of {:>5} conversations ({})", report.empty, report.conversations, percent(report.empty, report.conversations));
+    println!("  warmup  {:>5} of {:>5} agent transcripts ({})", report.warmup, report.agents, percent(report.warmup, report.agents));
+}
+
+/// Activity over time across all scanned workspaces, `conversations` should include agent transcripts
+pub fn print(conversations: &[Conversation], period: Period, last: u32, format: ReportFormat) -> Result<()> {
+    let report = build(conversations, period, last);
+    match format {
+        ReportFormat::Text => {
+            if report.conversations == 0 {
+                println!("{}", "No conversations found.".yellow());
+                return Ok(());
+            }
+            print_text(&report);
+        }
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use regex::{Regex, RegexBuilder};
+
+use crate::export::{format_time, parse_turns, role_label, Turn};
+use crate::{get_display_title, get_short_workspace, Conversation};
+
+/// Matching messages shown per conversation before collapsing the rest into a count
+const MAX_SNIPPETS: usize = 3;
+/// Longest line printed in a snippet, the rest is cut around the match
+const MAX_LINE_CHARS: usize = 160;
+
+pub struct SearchOptions {
+    pub regex: bool,
+    pub ignore_case: bool,
+    pub context: usize,
+}
+
+fn build_matcher(query: &str, opts: &SearchOptions) -> Result<Regex> {
+    let pattern = if opts.regex { query.to_string() } else { regex::escape(query) };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(opts.ignore_case)
+        .build()
+        .with_context(|| format!("Invalid search pattern '{}'", query))
+}
+
+/// A line trimmed to `MAX_LINE_CHARS` around the first match, with matches highlighted
+fn format_line(line: &str, matcher: &Regex) -> String {
+    let line = line.replace('\t', " ");
+    let chars: Vec<char> = line.chars().collect();
+    let shown: String = if chars.len() > MAX_LINE_CHARS {
+        let match_pos = matcher.find(&line).map(|m| line[..m.start()].chars().count()).unwrap_or(0);
+        let start = match_pos.saturating_sub(MAX_LINE_CHARS / 3).min(chars.len() - MAX_LINE_CHARS);
+        let mut s: String = chars[start..start + MAX_LINE_CHARS].iter().collect();
+        if start > 0 { s.insert_str(0, "..."); }
+        if start + MAX_LINE_CHARS < chars.len() { s.push_str("..."); }
+        s
+    } else {
+        line
+    };
+    matcher.replace_all(&shown, |caps: &regex::Captures| caps[0].red().bold().to_string()).to_string()
+}
+
+/// Print the lines of `text` each match spans, with `context` lines around them
+fn print_snippet(text: &str, matcher: &Regex, context: usize) {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return;
+    }
+    // Matches are found in the whole text, a pattern like `foo\nbar` spans several lines
+    let starts: Vec<usize> = std::iter::once(0).chain(text.match_indices('\n').map(|(i, _)| i + 1)).collect();
+    let line_of = |offset: usize| (starts.partition_point(|&s| s <= offset) - 1).min(lines.len() - 1);
+    let mut last_printed: Option<usize> = None;
+
+    for m in matcher.find_iter(text) {
+        let first = line_of(m.start());
+        let last = line_of(m.end().saturating_sub(1).max(m.start()));
+        let start = first.saturating_sub(context);
+        let end = (last + context).min(lines.len() - 1);
+        if last_printed.is_some_and(|printed| start > printed + 1) {
+            println!("       {}", "--".dimmed());
+        }
+        let from = last_printed.map_or(start, |printed| start.max(printed + 1));
+        for (j, l) in lines.iter().enumerate().take(end + 1).skip(from) {
+            if (first..=last).contains(&j) {
+                println!("       {}", format_line(l, matcher));
+            } else {
+                println!("       {}", format_line(l, matcher).dimmed());
+            }
+        }
+        last_printed = Some(last_printed.map_or(end, |printed| printed.max(end)));
+    }
+}
+
+/// Search the user and assistant messages of every conversation. Returns the conversations that matched.
+pub fn search(conversations: Vec<Conversation>, query: &str, opts: &SearchOptions) -> Result<Vec<Conversation>> {
+    let matcher = build_matcher(query, opts)?;
+    let mut matched = Vec::new();
+    let mut total_hits = 0;
+
+    for conv in conversations {
+        if conv.is_empty {
+            continue;
+        }
+        let Ok(turns) = parse_turns(&conv.path) else { continue };
+
+        let hits: Vec<(&Turn, String)> = turns.iter()
+            .flat_map(|turn| turn.blocks.iter().map(move |b| (turn, b.plain_text())))
+            .filter(|(_, text)| matcher.is_match(text))
+            .collect();
+        if hits.is_empty() {
+            continue;
+        }
+
+        println!(
+            "{} {} ({})  {}",
+            "->".green(),
+            get_display_title(&conv).bold(),
+            get_short_workspace(&conv.workspace_path).cyan(),
+            format!("{}  {} matches", conv.session_id, hits.len()).dimmed()
+        );
+        for (turn, text) in hits.iter().take(MAX_SNIPPETS) {
+            println!(
+                "     {}",
+                format!("L{} {} {}", turn.line, role_label(&turn.role), format_time(turn.timestamp)).yellow()
+            );
+            print_snippet(text, &matcher, opts.context);
+        }
+        if hits.len() > MAX_SNIPPETS {
+            println!("     {}", format!("... {} more", hits.len() - MAX_SNIPPETS).dimmed());
+        }
+        println!();
+
+        total_hits += hits.len();
+        matched.push(conv);
+    }
+
+    if matched.is_empty() {
+        println!("{}", "No matches found.".yellow());
+    } else {
+        println!("{} matches in {} conversations", total_hits.to_string().bold(), matched.len().to_string().bold());
+    }
+
+    Ok(matched)
+}
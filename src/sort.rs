@@ -0,0 +1,91 @@
+use clap::ValueEnum;
+use std::cmp::Ordering;
+
+use crate::{get_display_title, last_active, Conversation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    /// Newest last message first, titled conversations before untitled and empty ones
+    LastActive,
+    /// Newest first message first
+    FirstMessage,
+    /// Largest first, counting the session folder and agent files
+    Size,
+    /// Most user and assistant messages first
+    Messages,
+    /// Workspace path A-Z, newest first within a workspace
+    Workspace,
+    /// Title A-Z
+    Title,
+}
+
+impl SortKey {
+    /// The key `s` switches to
+    pub fn next(self) -> SortKey {
+        match self {
+            SortKey::LastActive => SortKey::FirstMessage,
+            SortKey::FirstMessage => SortKey::Size,
+            SortKey::Size => SortKey::Messages,
+            SortKey::Messages => SortKey::Workspace,
+            SortKey::Workspace => SortKey::Title,
+            SortKey::Title => SortKey::LastActive,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::LastActive => "last active",
+            SortKey::FirstMessage => "first message",
+            SortKey::Size => "size",
+            SortKey::Messages => "messages",
+            SortKey::Workspace => "workspace",
+            SortKey::Title => "title",
+        }
+    }
+}
+
+/// Newest first, conversations without a time at the end
+fn newest_first<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// The order `scan_conversations` returns: has title first, then no title, then empty.
+/// Within each group by last message, newest first.
+pub fn default_cmp(a: &Conversation, b: &Conversation) -> Ordering {
+    let priority = |c: &Conversation| {
+        if c.is_empty { 2 }
+        else if c.title.is_none() || c.title.as_deref() == Some("[No title]") { 1 }
+        else { 0 }
+    };
+    priority(a).cmp(&priority(b))
+        .then_with(|| newest_first(a.timestamp, b.timestamp))
+        .then_with(|| a.path.cmp(&b.path))
+}
+
+fn compare(a: &Conversation, b: &Conversation, key: SortKey) -> Ordering {
+    match key {
+        SortKey::LastActive => default_cmp(a, b),
+        SortKey::FirstMessage => newest_first(a.started, b.started),
+        SortKey::Size => b.total_size.cmp(&a.total_size),
+        SortKey::Messages => b.message_count.cmp(&a.message_count),
+        SortKey::Workspace => a.workspace_path.cmp(&b.workspace_path)
+            .then_with(|| newest_first(last_active(a), last_active(b))),
+        SortKey::Title => get_display_title(a).to_lowercase().cmp(&get_display_title(b).to_lowercase()),
+    }
+    .then_with(|| a.path.cmp(&b.path))
+}
+
+/// Indices of `conversations` in display order for `key`, flipped when `reverse` is set
+pub fn order(conversations: &[Conversation], key: SortKey, reverse: bool) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..conversations.len()).collect();
+    order.sort_by(|&a, &b| compare(&conversations[a], &conversations[b], key));
+    if reverse {
+        order.reverse();
+    }
+    order
+}
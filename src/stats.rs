@@ -0,0 +1,427 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::config::{self, Price};
+use crate::{display_root, get_display_title, get_short_workspace, subagent_paths, Conversation};
+
+/// USD per million tokens by model name prefix, used unless the config's `[prices]` says otherwise
+const BUILTIN_PRICES: &[(&str, Price)] = &[
+    ("claude-opus-4-5", Price { input: 5.0, output: 25.0, cache_read: 0.5, cache_write: 6.25 }),
+    ("claude-opus-4", Price { input: 15.0, output: 75.0, cache_read: 1.5, cache_write: 18.75 }),
+    ("claude-sonnet-4", Price { input: 3.0, output: 15.0, cache_read: 0.3, cache_write: 3.75 }),
+    ("claude-3-7-sonnet", Price { input: 3.0, output: 15.0, cache_read: 0.3, cache_write: 3.75 }),
+    ("claude-haiku-4", Price { input: 1.0, output: 5.0, cache_read: 0.1, cache_write: 1.25 }),
+    ("claude-3-5-haiku", Price { input: 0.8, output: 4.0, cache_read: 0.08, cache_write: 1.0 }),
+];
+/// Model name Claude Code writes on messages it made up itself (errors, interruptions), they cost nothing
+const SYNTHETIC_MODEL: &str = "<synthetic>";
+/// Tools listed in the workspace report, the rest are summed up as "other"
+const TOP_TOOLS: usize = 10;
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+struct Usage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsEntry {
+    #[serde(rename = "type")]
+    entry_type: Option<String>,
+    message: Option<StatsMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsMessage {
+    id: Option<String>,
+    model: Option<String>,
+    content: Option<serde_json::Value>,
+    usage: Option<Usage>,
+}
+
+/// Tokens spent with one model
+#[derive(Debug, Default, Clone, Copy)]
+struct ModelUsage {
+    messages: usize,
+    input: u64,
+    output: u64,
+    cache_read: u64,
+    cache_write: u64,
+}
+
+impl ModelUsage {
+    fn add(&mut self, other: &ModelUsage) {
+        self.messages += other.messages;
+        self.input += other.input;
+        self.output += other.output;
+        self.cache_read += other.cache_read;
+        self.cache_write += other.cache_write;
+    }
+
+    fn cost(&self, price: &Price) -> f64 {
+        (self.input as f64 * price.input
+            + self.output as f64 * price.output
+            + self.cache_read as f64 * price.cache_read
+            + self.cache_write as f64 * price.cache_write)
+            / 1_000_000.0
+    }
+}
+
+/// Counts over one or more transcripts
+#[derive(Debug, Default)]
+struct Stats {
+    conversations: usize,
+    user_turns: usize,
+    assistant_turns: usize,
+    tool_calls: BTreeMap<String, usize>,
+    models: BTreeMap<String, ModelUsage>,
+}
+
+impl Stats {
+    fn merge(&mut self, other: Stats) {
+        self.conversations += other.conversations;
+        self.user_turns += other.user_turns;
+        self.assistant_turns += other.assistant_turns;
+        for (name, n) in other.tool_calls {
+            *self.tool_calls.entry(name).or_default() += n;
+        }
+        for (model, usage) in other.models {
+            self.models.entry(model).or_default().add(&usage);
+        }
+    }
+
+    fn tokens(&self) -> ModelUsage {
+        let mut total = ModelUsage::default();
+        for usage in self.models.values() {
+            total.add(usage);
+        }
+        total
+    }
+
+    /// Estimated cost, and the models it leaves out for lack of a price
+    fn cost(&self) -> (f64, Vec<&str>) {
+        let mut cost = 0.0;
+        let mut unpriced = Vec::new();
+        for (model, usage) in &self.models {
+            match price_for(model) {
+                Some(price) => cost += usage.cost(&price),
+                None => unpriced.push(model.as_str()),
+            }
+        }
+        (cost, unpriced)
+    }
+}
+
+/// Price of the longest matching model prefix, the config's `[prices]` winning ties with the built-in table
+fn price_for(model: &str) -> Option<Price> {
+    if model == SYNTHETIC_MODEL {
+        return Some(Price::default());
+    }
+    let configured = config::get().prices.iter().map(|(prefix, price)| (prefix.as_str(), *price));
+    configured
+        .chain(BUILTIN_PRICES.iter().copied())
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .fold(None::<(&str, Price)>, |best, (prefix, price)| match best {
+            Some((b, _)) if b.len() >= prefix.len() => best,
+            _ => Some((prefix, price)),
+        })
+        .map(|(_, price)| price)
+}
+
+/// A user entry with text in it, rather than one that only carries tool results
+fn is_user_turn(content: Option<&serde_json::Value>) -> bool {
+    match content {
+        Some(serde_json::Value::String(s)) => !s.is_empty(),
+        Some(serde_json::Value::Array(items)) => items.iter().any(|i| i.get("type").and_then(|t| t.as_str()) == Some("text")),
+        _ => false,
+    }
+}
+
+/// Count turns, tool calls and tokens of one transcript. Claude Code writes each content block of an
+/// assistant message on its own line, repeating the message ID and usage, so those are counted once per ID.
+fn read_transcript(path: &Path, stats: &mut Stats) -> Result<()> {
+    let file = fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut messages: HashMap<String, (String, Usage)> = HashMap::new();
+
+    // Split on bytes, so a line that isn't valid UTF-8 is decoded lossily instead of ending the count
+    for line in BufReader::new(file).split(b'\n') {
+        let Ok(line) = line else { break };
+        let Ok(entry) = serde_json::from_str::<StatsEntry>(&String::from_utf8_lossy(&line)) else { continue };
+        let Some(message) = entry.message else { continue };
+
+        match entry.entry_type.as_deref() {
+            Some("user") if is_user_turn(message.content.as_ref()) => stats.user_turns += 1,
+            Some("assistant") => {
+                if let Some(serde_json::Value::Array(items)) = &message.content {
+                    for item in items.iter().filter(|i| i.get("type").and_then(|t| t.as_str()) == Some("tool_use")) {
+                        let name = item.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+                        *stats.tool_calls.entry(name.to_string()).or_default() += 1;
+                    }
+                }
+
+                let model = message.model.unwrap_or_else(|| "unknown".to_string());
+                let usage = message.usage.unwrap_or_default();
+                match message.id {
+                    // Later lines of a message carry the final output token count
+                    Some(id) => { messages.insert(id, (model, usage)); }
+                    None => { messages.insert(format!("line-{}", messages.len()), (model, usage)); }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    stats.assistant_turns += messages.len();
+    for (model, usage) in messages.into_values() {
+        stats.models.entry(model).or_default().add(&ModelUsage {
+            messages: 1,
+            input: usage.input_tokens,
+            output: usage.output_tokens,
+            cache_read: usage.cache_read_input_tokens,
+            cache_write: usage.cache_creation_input_tokens,
+        });
+    }
+    Ok(())
+}
+
+/// Stats of a conversation including its subagents
+fn conversation_stats(conv: &Conversation) -> Result<Stats> {
+    let mut stats = Stats { conversations: 1, ..Stats::default() };
+    if !conv.is_empty {
+        read_transcript(&conv.path, &mut stats)?;
+    }
+    for path in subagent_paths(conv)? {
+        read_transcript(&path, &mut stats)?;
+    }
+    Ok(stats)
+}
+
+/// `1234` -> `1.2k`, `5600000` -> `5.6M`
+fn format_count(n: u64) -> String {
+    match n {
+        0..=999 => n.to_string(),
+        1_000..=999_999 => format!("{:.1}k", n as f64 / 1e3),
+        1_000_000..=999_999_999 => format!("{:.1}M", n as f64 / 1e6),
+        _ => format!("{:.1}B", n as f64 / 1e9),
+    }
+}
+
+fn format_cost(cost: f64, unpriced: &[&str]) -> String {
+    format!("${:.2}{}", cost, if unpriced.is_empty() { "" } else { "*" })
+}
+
+fn print_unpriced(unpriced: &[&str]) {
+    if !unpriced.is_empty() {
+        println!();
+        println!("{}", format!("* without tokens of {}, add them under [prices] in the config file", unpriced.join(", ")).dimmed());
+    }
+}
+
+fn print_tools(tool_calls: &BTreeMap<String, usize>, limit: usize) {
+    let mut tools: Vec<(&String, &usize)> = tool_calls.iter().collect();
+    tools.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    for (name, n) in tools.iter().take(limit) {
+        println!("  {:>8}  {}", n, name);
+    }
+    let rest: usize = tools.iter().skip(limit).map(|(_, n)| **n).sum();
+    if rest > 0 {
+        println!("  {:>8}  {}", rest, "other".dimmed());
+    }
+}
+
+fn print_models(models: &BTreeMap<String, ModelUsage>) {
+    println!(
+        "  {:>8} {:>9} {:>9} {:>9} {:>9} {:>10}  {}",
+        "MSGS".dimmed(), "INPUT".dimmed(), "OUTPUT".dimmed(), "CACHE R".dimmed(), "CACHE W".dimmed(), "COST".dimmed(), "MODEL".dimmed()
+    );
+    for (model, usage) in models {
+        let cost = price_for(model).map(|p| format!("${:.2}", usage.cost(&p))).unwrap_or_else(|| "?".to_string());
+        println!(
+            "  {:>8} {:>9} {:>9} {:>9} {:>9} {:>10}  {}",
+            usage.messages,
+            format_count(usage.input),
+            format_count(usage.output),
+            format_count(usage.cache_read),
+            format_count(usage.cache_write),
+            cost,
+            model
+        );
+    }
+}
+
+/// Turns, tool calls, models, tokens and cost of one conversation and its subagents
+pub fn show(conv: &Conversation) -> Result<()> {
+    let stats = conversation_stats(conv)?;
+    let (cost, unpriced) = stats.cost();
+
+    println!("{}", get_display_title(conv).bold().cyan());
+    println!("{}", format!("{}  {}", conv.session_id, conv.workspace_path).dimmed());
+    println!();
+    println!("  Turns:       {} user, {} assistant", stats.user_turns, stats.assistant_turns);
+    println!("  Subagents:   {}", conv.agent_count);
+    println!("  Tool calls:  {}", stats.tool_calls.values().sum::<usize>());
+    let tokens = stats.tokens();
+    println!("  Tokens:      {} in, {} out, {} cache read, {} cache write",
+        format_count(tokens.input), format_count(tokens.output), format_count(tokens.cache_read), format_count(tokens.cache_write));
+    println!("  Est. cost:   {}", format_cost(cost, &unpriced).bold());
+
+    if !stats.models.is_empty() {
+        println!();
+        println!("{}", "Models:".bold().cyan());
+        print_models(&stats.models);
+    }
+    if !stats.tool_calls.is_empty() {
+        println!();
+        println!("{}", "Tool calls:".bold().cyan());
+        print_tools(&stats.tool_calls, usize::MAX);
+    }
+    print_unpriced(&unpriced);
+    Ok(())
+}
+
+fn print_row(label: &str, stats: &Stats) {
+    let tokens = stats.tokens();
+    let (cost, unpriced) = stats.cost();
+    println!(
+        "  {:>6} {:>8} {:>8} {:>9} {:>9} {:>9} {:>9} {:>10}  {}",
+        stats.conversations,
+        stats.user_turns + stats.assistant_turns,
+        stats.tool_calls.values().sum::<usize>(),
+        format_count(tokens.input),
+        format_count(tokens.output),
+        format_count(tokens.cache_read),
+        format_count(tokens.cache_write),
+        format_cost(cost, &unpriced).bold(),
+        label
+    );
+}
+
+/// Token usage and cost per workspace, most expensive first, then totals by model and tool
+pub fn report(conversations: &[Conversation], several_roots: bool) -> Result<()> {
+    if conversations.is_empty() {
+        println!("{}", "No conversations found.".yellow());
+        return Ok(());
+    }
+
+    let per_conversation = conversations.par_iter()
+        .map(|conv| conversation_stats(conv).map(|stats| (conv, stats)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut workspaces: BTreeMap<String, Stats> = BTreeMap::new();
+    for (conv, stats) in per_conversation {
+        let label = if several_roots {
+            format!("{} [{}]", get_short_workspace(&conv.workspace_path), display_root(&conv.root))
+        } else {
+            conv.workspace_path.clone()
+        };
+        workspaces.entry(label).or_default().merge(stats);
+    }
+
+    let mut rows: Vec<(String, Stats, f64)> = workspaces.into_iter()
+        .map(|(label, stats)| {
+            let cost = stats.cost().0;
+            (label, stats, cost)
+        })
+        .collect();
+    rows.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| b.1.tokens().output.cmp(&a.1.tokens().output)));
+
+    println!("{}", "Token usage by workspace:".bold().cyan());
+    println!();
+    println!(
+        "  {:>6} {:>8} {:>8} {:>9} {:>9} {:>9} {:>9} {:>10}  {}",
+        "CHATS".dimmed(), "TURNS".dimmed(), "TOOLS".dimmed(), "INPUT".dimmed(), "OUTPUT".dimmed(),
+        "CACHE R".dimmed(), "CACHE W".dimmed(), "COST".dimmed(), "WORKSPACE".dimmed()
+    );
+
+    let mut totals = Stats::default();
+    for (label, stats, _) in rows {
+        print_row(&label, &stats);
+        totals.merge(stats);
+    }
+    println!("  {}", "-".repeat(90).dimmed());
+    print_row("total", &totals);
+
+    let unpriced = totals.cost().1;
+    if !totals.models.is_empty() {
+        println!();
+        println!("{}", "By model:".bold().cyan());
+        print_models(&totals.models);
+    }
+    if !totals.tool_calls.is_empty() {
+        println!();
+        println!("{}", "Most used tools:".bold().cyan());
+        print_tools(&totals.tool_calls, TOP_TOOLS);
+    }
+    print_unpriced(&unpriced);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_claude_dir;
+
+    /// Counts of a transcript made of `lines`
+    fn stats(name: &str, lines: &[&[u8]]) -> Stats {
+        let dir = temp_claude_dir(name);
+        let path = dir.join("t.jsonl");
+        fs::write(&path, lines.join(&b'\n')).unwrap();
+        let mut stats = Stats::default();
+        read_transcript(&path, &mut stats).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        stats
+    }
+
+    fn assistant(id: &str, block: &str, output_tokens: u64) -> String {
+        format!(
+            r#"{{"type":"assistant","message":{{"id":"{}","model":"claude-sonnet-4-5","content":[{}],"usage":{{"input_tokens":10,"output_tokens":{}}}}}}}"#,
+            id, block, output_tokens
+        )
+    }
+
+    #[test]
+    fn repeated_message_ids_count_once() {
+        let thinking = assistant("msg_1", r#"{"type":"thinking","thinking":"hmm"}"#, 1);
+        let text = assistant("msg_1", r#"{"type":"text","text":"hi"}"#, 1);
+        let tool = assistant("msg_1", r#"{"type":"tool_use","id":"t1","name":"Bash","input":{}}"#, 40);
+        let other = assistant("msg_2", r#"{"type":"text","text":"done"}"#, 5);
+        let stats = stats("stats-message-ids", &[thinking.as_bytes(), text.as_bytes(), tool.as_bytes(), other.as_bytes()]);
+
+        assert_eq!(stats.assistant_turns, 2);
+        assert_eq!(stats.tool_calls["Bash"], 1);
+        let usage = stats.models["claude-sonnet-4-5"];
+        assert_eq!((usage.messages, usage.input, usage.output), (2, 20, 45), "the last line of a message has its final usage");
+    }
+
+    #[test]
+    fn tool_results_are_not_user_turns() {
+        let stats = stats("stats-tool-results", &[
+            br#"{"type":"user","message":{"role":"user","content":"fix it"}}"#,
+            br#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"ok"}]}}"#,
+            br#"{"type":"user","message":{"role":"user","content":[{"type":"text","text":"and now?"}]}}"#,
+        ]);
+        assert_eq!(stats.user_turns, 2);
+    }
+
+    #[test]
+    fn counting_goes_on_past_invalid_utf8() {
+        let stats = stats("stats-invalid-utf8", &[
+            br#"{"type":"user","message":{"role":"user","content":"one"}}"#,
+            b"{\"type\":\"user\",\"message\":{\"role\":\"user\",\"content\":\"caf\xe9\"}}",
+            br#"{"type":"user","message":{"role":"user","content":"three"}}"#,
+        ]);
+        assert_eq!(stats.user_turns, 3);
+    }
+}
@@ -0,0 +1,45 @@
+//! Temp `.claude` folders for the tests that move, rewrite or restore real files
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{scan_conversations, Conversation};
+
+/// A fresh `.claude` folder with an empty `projects/` under the system temp dir
+pub fn temp_claude_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("chc-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("projects")).unwrap();
+    dir
+}
+
+/// Write `projects/<workspace>/<session_id>.jsonl` with one user prompt per line of `prompts`
+pub fn write_transcript(claude_dir: &Path, workspace: &str, session_id: &str, prompts: &[&str]) -> PathBuf {
+    let folder = claude_dir.join("projects").join(workspace);
+    fs::create_dir_all(&folder).unwrap();
+    let lines: Vec<String> = prompts.iter().enumerate()
+        .map(|(i, prompt)| serde_json::json!({
+            "type": "user",
+            "uuid": format!("{}-{}", session_id, i),
+            "sessionId": session_id,
+            "timestamp": format!("2026-01-01T00:00:0{}Z", i),
+            "message": {"role": "user", "content": prompt},
+        }).to_string())
+        .collect();
+    let path = folder.join(format!("{}.jsonl", session_id));
+    fs::write(&path, lines.join("\n") + "\n").unwrap();
+    path
+}
+
+/// An index line for `session_id`, as Claude Code writes them to `history.jsonl`
+pub fn history_line(session_id: &str, timestamp: i64) -> String {
+    format!(r#"{{"display":"prompt","pastedContents":{{}},"timestamp":{},"project":"/tmp/ws","sessionId":"{}"}}"#, timestamp, session_id)
+}
+
+/// The conversation `session_id` as a scan of `claude_dir` finds it
+pub fn conversation(claude_dir: &Path, session_id: &str) -> Conversation {
+    scan_conversations(&[claude_dir.join("projects")], None, true).unwrap()
+        .into_iter()
+        .find(|c| c.session_id == session_id)
+        .unwrap()
+}
@@ -0,0 +1,442 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local, Utc};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    chc_data_dir, confirm_deletion, format_size, get_display_title, get_short_workspace, history, older_than_cutoff,
+    path_size, Conversation,
+};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const FILES_DIR: &str = "files";
+
+/// A single file or folder moved into a trash entry
+#[derive(Debug, Serialize, Deserialize)]
+struct TrashItem {
+    /// Where the item lived before it was trashed
+    original: PathBuf,
+    /// Name of the item inside the entry's `files/` folder
+    stored: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    session_id: String,
+    workspace_path: String,
+    title: String,
+    deleted_at: DateTime<Utc>,
+    items: Vec<TrashItem>,
+    /// `.claude` folder the conversation was deleted from
+    #[serde(default)]
+    claude_dir: Option<PathBuf>,
+    /// Lines removed from `history.jsonl`, put back on restore
+    #[serde(default)]
+    history: Vec<String>,
+}
+
+struct TrashEntry {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl TrashEntry {
+    fn size(&self) -> u64 {
+        path_size(&self.dir.join(FILES_DIR))
+    }
+}
+
+fn trash_dir() -> Result<PathBuf> {
+    Ok(chc_data_dir()?.join("trash"))
+}
+
+fn write_manifest(dir: &Path, manifest: &Manifest) -> Result<()> {
+    let json = serde_json::to_string_pretty(manifest)?;
+    fs::write(dir.join(MANIFEST_FILE), json)
+        .with_context(|| format!("Failed to write trash manifest in {}", dir.display()))
+}
+
+/// Rename, falling back to copy + remove when source and trash live on different filesystems
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_then_remove(from, to)
+}
+
+fn copy_then_remove(from: &Path, to: &Path) -> Result<()> {
+    if let Err(e) = copy_recursive(from, to) {
+        // A partial copy is in no manifest, nothing would ever clean it up
+        let _ = remove_path(to);
+        return Err(e);
+    }
+    if let Err(e) = remove_path(from) {
+        // Don't leave a second copy behind if the original can't be removed
+        let _ = remove_path(to);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) }
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// Trashed conversations whose `history.jsonl` entries are still to be removed.
+/// The index is rewritten once for the whole batch when it is finished.
+#[derive(Default)]
+pub struct HistoryBatch {
+    /// `.claude` folder, session ID and trash entry of each conversation
+    pending: Vec<(PathBuf, String, PathBuf)>,
+}
+
+impl HistoryBatch {
+    /// Remove the index entries of every conversation in the batch and record them in its trash entry
+    pub fn finish(self) {
+        let mut by_dir: HashMap<PathBuf, Vec<(String, PathBuf)>> = HashMap::new();
+        for (claude_dir, session_id, entry_dir) in self.pending {
+            by_dir.entry(claude_dir).or_default().push((session_id, entry_dir));
+        }
+
+        for (claude_dir, sessions) in by_dir {
+            let ids: Vec<String> = sessions.iter().map(|(id, _)| id.clone()).collect();
+            let mut removed = match history::remove_sessions(&claude_dir, &ids) {
+                Ok(removed) => removed,
+                Err(e) => {
+                    eprintln!("  {} Could not update history.jsonl: {}", "WARN".yellow(), e);
+                    continue;
+                }
+            };
+            for (session_id, entry_dir) in sessions {
+                let Some(lines) = removed.remove(&session_id) else { continue };
+                if let Err(e) = record_history(&entry_dir, lines) {
+                    eprintln!("  {} Could not keep the history.jsonl entries of {}: {}", "WARN".yellow(), session_id, e);
+                }
+            }
+        }
+    }
+}
+
+fn record_history(dir: &Path, lines: Vec<String>) -> Result<()> {
+    let content = fs::read_to_string(dir.join(MANIFEST_FILE))?;
+    let mut manifest: Manifest = serde_json::from_str(&content)?;
+    manifest.history = lines;
+    write_manifest(dir, &manifest)
+}
+
+/// Move a conversation's files into a new trash entry. Returns the number of transcripts moved.
+pub fn move_to_trash(conv: &Conversation, files: &[PathBuf], batch: &mut HistoryBatch) -> Result<usize> {
+    store(&conv.session_id, &conv.workspace_path, get_display_title(conv), &conv.root, files, |dir, manifest| {
+        // Only main conversations have index entries, and only once the transcript itself is gone
+        if !conv.session_id.starts_with("agent-") && !conv.path.exists() {
+            if let Some(ref claude_dir) = manifest.claude_dir {
+                batch.pending.push((claude_dir.clone(), conv.session_id.clone(), dir.to_path_buf()));
+            }
+        }
+    })
+}
+
+/// Move files that belong to no conversation into a new trash entry, restorable under `name`
+pub fn move_orphan(name: &str, workspace_path: &str, title: String, claude_dir: &Path, files: &[PathBuf]) -> Result<usize> {
+    store(name, workspace_path, title, claude_dir, files, |_, _| ())
+}
+
+/// Move `files` into a new trash entry. `finish` gets the entry's folder once something was moved, before the manifest is written.
+fn store(
+    session_id: &str,
+    workspace_path: &str,
+    title: String,
+    claude_dir: &Path,
+    files: &[PathBuf],
+    finish: impl FnOnce(&Path, &mut Manifest),
+) -> Result<usize> {
+    let deleted_at = Utc::now();
+    let id = format!("{}-{}", deleted_at.format("%Y%m%dT%H%M%S%.3f"), session_id);
+    let dir = trash_dir()?.join(id);
+    let files_dir = dir.join(FILES_DIR);
+    fs::create_dir_all(&files_dir).with_context(|| format!("Failed to create trash folder {}", files_dir.display()))?;
+
+    let mut manifest = Manifest {
+        session_id: session_id.to_string(),
+        workspace_path: workspace_path.to_string(),
+        title,
+        deleted_at,
+        items: Vec::new(),
+        claude_dir: Some(claude_dir.to_path_buf()),
+        history: Vec::new(),
+    };
+
+    let mut moved = 0;
+    let mut result = Ok(());
+    for path in files {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+        if let Err(e) = move_path(path, &files_dir.join(&name)) {
+            result = Err(e.context(format!("Failed to move {} to trash", path.display())));
+            break;
+        }
+        if path.extension().and_then(|e| e.to_str()) == Some("jsonl") {
+            moved += 1;
+        }
+        manifest.items.push(TrashItem { original: path.clone(), stored: name });
+    }
+
+    if manifest.items.is_empty() {
+        let _ = fs::remove_dir_all(&dir);
+    } else {
+        finish(&dir, &mut manifest);
+
+        // Record whatever made it into the trash, even if a later item failed
+        write_manifest(&dir, &manifest)?;
+    }
+
+    result.map(|_| moved)
+}
+
+fn load_entries() -> Result<Vec<TrashEntry>> {
+    let trash = trash_dir()?;
+    if !trash.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(&trash)?.filter_map(|e| e.ok()) {
+        let dir = entry.path();
+        let Ok(content) = fs::read_to_string(dir.join(MANIFEST_FILE)) else { continue };
+        match serde_json::from_str::<Manifest>(&content) {
+            Ok(manifest) => entries.push(TrashEntry { dir, manifest }),
+            Err(e) => eprintln!("  {} Skipping {}: {}", "WARN".yellow(), dir.display(), e),
+        }
+    }
+
+    // Newest first
+    entries.sort_by_key(|e| std::cmp::Reverse(e.manifest.deleted_at));
+    Ok(entries)
+}
+
+pub fn list() -> Result<()> {
+    let entries = load_entries()?;
+    if entries.is_empty() {
+        println!("{}", "Trash is empty.".yellow());
+        return Ok(());
+    }
+
+    let total_size: u64 = entries.iter().map(|e| e.size()).sum();
+    println!("{} ({} conversations, {})", "Trash:".bold().cyan(), entries.len().to_string().yellow(), format_size(total_size));
+    println!();
+
+    for entry in &entries {
+        let m = &entry.manifest;
+        println!(
+            "  {} {} {} ({})",
+            "->".green(),
+            m.deleted_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string().dimmed(),
+            m.title,
+            get_short_workspace(&m.workspace_path)
+        );
+        println!(
+            "     {}",
+            format!("{}  {} items, {}", m.session_id, m.items.len(), format_size(entry.size())).dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn restore(session: &str, dry_run: bool) -> Result<()> {
+    let entries = load_entries()?;
+
+    let exact: Vec<&TrashEntry> = entries.iter().filter(|e| e.manifest.session_id == session).collect();
+    let matches = if exact.is_empty() {
+        entries.iter().filter(|e| e.manifest.session_id.starts_with(session)).collect()
+    } else {
+        exact
+    };
+
+    let Some(entry) = matches.first() else {
+        anyhow::bail!("No trashed conversation matches '{}'", session);
+    };
+    if matches.iter().any(|e| e.manifest.session_id != entry.manifest.session_id) {
+        anyhow::bail!("'{}' matches more than one trashed conversation, use the full session ID", session);
+    }
+
+    // Refuse to overwrite anything that was recreated since
+    for item in &entry.manifest.items {
+        if item.original.exists() {
+            anyhow::bail!("Cannot restore, {} already exists", item.original.display());
+        }
+    }
+
+    if dry_run {
+        println!("Would restore {} ({}):", entry.manifest.title, entry.manifest.workspace_path.dimmed());
+        for item in &entry.manifest.items {
+            println!("  {} {}", "->".green(), item.original.display());
+        }
+        if !entry.manifest.history.is_empty() {
+            println!("  {} {} history.jsonl entries", "->".green(), entry.manifest.history.len());
+        }
+        println!();
+        println!("{}", "Dry run, nothing was restored.".yellow());
+        return Ok(());
+    }
+
+    let files_dir = entry.dir.join(FILES_DIR);
+    for item in &entry.manifest.items {
+        if let Some(parent) = item.original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_path(&files_dir.join(&item.stored), &item.original)
+            .with_context(|| format!("Failed to restore {}", item.original.display()))?;
+    }
+
+    if let Some(ref claude_dir) = entry.manifest.claude_dir {
+        if let Err(e) = history::restore_lines(claude_dir, &entry.manifest.history) {
+            eprintln!("  {} Could not restore history.jsonl entries: {}", "WARN".yellow(), e);
+        }
+    }
+
+    fs::remove_dir_all(&entry.dir).with_context(|| format!("Failed to remove trash entry {}", entry.dir.display()))?;
+
+    println!(
+        "{} Restored {} ({})",
+        "OK".green(),
+        entry.manifest.title,
+        entry.manifest.workspace_path.dimmed()
+    );
+    Ok(())
+}
+
+pub fn empty(older_than: Option<Duration>, dry_run: bool, yes: bool) -> Result<()> {
+    let entries = load_entries()?;
+    let cutoff = older_than.map(older_than_cutoff).transpose()?;
+    let to_remove: Vec<&TrashEntry> = entries.iter()
+        .filter(|e| cutoff.is_none_or(|c| e.manifest.deleted_at < c))
+        .collect();
+
+    if to_remove.is_empty() {
+        println!("{}", "Nothing to remove from trash.".yellow());
+        return Ok(());
+    }
+
+    let total_size: u64 = to_remove.iter().map(|e| e.size()).sum();
+    if dry_run {
+        for entry in &to_remove {
+            println!("  - {} {} ({})", entry.manifest.session_id.dimmed(), entry.manifest.title, format_size(entry.size()));
+        }
+        println!();
+        println!("{}", format!("Dry run, would permanently delete {} trashed conversations ({}).", to_remove.len(), format_size(total_size)).yellow());
+        return Ok(());
+    }
+
+    let prompt = format!("Permanently delete {} trashed conversations ({})?", to_remove.len(), format_size(total_size));
+    if !confirm_deletion(prompt, yes)? {
+        println!("{}", "Cancelled.".yellow());
+        return Ok(());
+    }
+
+    let mut removed = 0;
+    let mut errors = 0;
+    for entry in to_remove {
+        match fs::remove_dir_all(&entry.dir) {
+            Ok(_) => removed += 1,
+            Err(e) => {
+                eprintln!("  {} Failed to remove {}: {}", "ERR".red(), entry.manifest.session_id, e);
+                errors += 1;
+            }
+        }
+    }
+
+    if errors > 0 {
+        println!("{} Removed {} trashed conversations ({} failed)", "WARN".yellow(), removed, errors);
+    } else {
+        println!("{} Removed {} trashed conversations", "OK".green(), removed);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation_files;
+    use crate::testing::{conversation, history_line, temp_claude_dir, write_transcript};
+
+    /// Trash a conversation the way deleting it does, index entries included
+    fn trash(claude_dir: &Path, session_id: &str) {
+        let conv = conversation(claude_dir, session_id);
+        let files = conversation_files(&conv).unwrap();
+        let mut batch = HistoryBatch::default();
+        move_to_trash(&conv, &files, &mut batch).unwrap();
+        batch.finish();
+    }
+
+    #[test]
+    fn trashed_conversation_comes_back_with_its_history() {
+        let dir = temp_claude_dir("trash-round-trip");
+        let session = "trash-round-trip-1";
+        let transcript = write_transcript(&dir, "-tmp-ws", session, &["first", "second"]);
+        let folder = transcript.with_extension("");
+        fs::create_dir_all(folder.join("subagents")).unwrap();
+        fs::write(folder.join("subagents").join("agent-a1.jsonl"), "{}\n").unwrap();
+        let content = fs::read(&transcript).unwrap();
+        let history = [history_line(session, 1), history_line("other", 2), history_line(session, 3)].join("\n") + "\n";
+        fs::write(dir.join("history.jsonl"), &history).unwrap();
+
+        trash(&dir, session);
+        assert!(!transcript.exists() && !folder.exists());
+        assert_eq!(fs::read_to_string(dir.join("history.jsonl")).unwrap(), history_line("other", 2) + "\n");
+
+        restore(session, false).unwrap();
+        assert_eq!(fs::read(&transcript).unwrap(), content);
+        assert_eq!(fs::read_to_string(folder.join("subagents").join("agent-a1.jsonl")).unwrap(), "{}\n");
+        assert_eq!(fs::read_to_string(dir.join("history.jsonl")).unwrap(), history);
+        assert!(restore(session, false).is_err(), "the trash entry is gone once restored");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restore_refuses_to_overwrite_a_recreated_file() {
+        let dir = temp_claude_dir("trash-overwrite");
+        let session = "trash-overwrite-1";
+        let transcript = write_transcript(&dir, "-tmp-ws", session, &["old"]);
+
+        trash(&dir, session);
+        fs::write(&transcript, "new\n").unwrap();
+
+        assert!(restore(session, false).is_err());
+        assert_eq!(fs::read_to_string(&transcript).unwrap(), "new\n");
+        let entry = load_entries().unwrap().into_iter().find(|e| e.manifest.session_id == session).unwrap();
+        assert!(entry.dir.join(FILES_DIR).join(format!("{}.jsonl", session)).exists(), "the trashed copy is kept");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_copy_leaves_nothing_behind() {
+        let dir = temp_claude_dir("trash-partial-copy");
+        let from = dir.join("source");
+        fs::create_dir_all(&from).unwrap();
+        fs::write(from.join("a.jsonl"), "a").unwrap();
+        // A dangling symlink can't be copied, so the copy fails partway
+        std::os::unix::fs::symlink(dir.join("missing"), from.join("b.jsonl")).unwrap();
+        let to = dir.join("copy");
+
+        assert!(copy_then_remove(&from, &to).is_err());
+        assert!(!to.exists());
+        assert!(from.join("a.jsonl").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
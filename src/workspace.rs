@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{chc_data_dir, decode_workspace_name, write_atomic, JsonlEntry};
+
+/// Lines read from the top of each transcript when looking for a `cwd` field
+const CWD_SCAN_LINES: usize = 20;
+/// Transcripts checked per workspace before falling back to the filesystem
+const CWD_SCAN_FILES: usize = 5;
+
+/// Resolved folder name -> project path, loaded from and saved to the chc data dir
+static CACHE: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+pub fn cache_path() -> Option<PathBuf> {
+    chc_data_dir().ok().map(|d| d.join("workspaces.json"))
+}
+
+/// Claude names a workspace folder after the project path with every non-alphanumeric character replaced by `-`
+pub fn encode_project_path(path: &str) -> String {
+    path.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '-' }).collect()
+}
+
+/// The project path recorded in the transcripts' `cwd` field. Claude keeps the session's `cwd`
+/// up to date, so a subdirectory of the project is walked up until it encodes to `name`.
+fn path_from_cwd(workspace_folder: &Path, name: &str) -> Option<String> {
+    let entries = fs::read_dir(workspace_folder).ok()?;
+    let transcripts = entries.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+        .take(CWD_SCAN_FILES);
+
+    for path in transcripts {
+        let Ok(file) = fs::File::open(&path) else { continue };
+        for line in BufReader::new(file).lines().take(CWD_SCAN_LINES).map_while(Result::ok) {
+            let Some(cwd) = serde_json::from_str::<JsonlEntry>(&line).ok().and_then(|e| e.cwd) else { continue };
+            if let Some(found) = Path::new(&cwd).ancestors().find(|p| encode_project_path(&p.to_string_lossy()) == name) {
+                return Some(found.to_string_lossy().to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Rebuild the path from existing directories, trying every way of splitting the `-`
+/// separated parts into path components (`my-app` vs `my/app`)
+fn path_from_filesystem(name: &str) -> Option<String> {
+    fn walk(dir: &Path, parts: &[&str]) -> Option<PathBuf> {
+        if parts.is_empty() {
+            return Some(dir.to_path_buf());
+        }
+        // Longest component first, so `my-app` wins over `my/app` when both exist
+        for take in (1..=parts.len()).rev() {
+            let candidate = dir.join(parts[..take].join("-"));
+            if candidate.is_dir() {
+                if let Some(found) = walk(&candidate, &parts[take..]) {
+                    return Some(found);
+                }
+            }
+        }
+        None
+    }
+
+    let rest = name.strip_prefix('-')?;
+    let parts: Vec<&str> = rest.split('-').collect();
+    let found = walk(Path::new("/"), &parts)?;
+    let found = found.to_string_lossy().to_string();
+    // Only trust it if it encodes back to the same folder name
+    (encode_project_path(&found) == name).then_some(found)
+}
+
+fn save(cache: &HashMap<String, String>) {
+    let Some(path) = cache_path() else { return };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_vec_pretty(cache) {
+        let _ = write_atomic(&path, &json);
+    }
+}
+
+/// The real project path of a workspace folder. Falls back to the lossy `-` -> `/` decoding
+/// when neither the transcripts nor the filesystem can tell.
+pub fn resolve(workspace_folder: &Path) -> String {
+    let name = workspace_folder.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+    let mut guard = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = guard.get_or_insert_with(|| {
+        cache_path()
+            .and_then(|p| fs::read(p).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    });
+
+    if let Some(path) = cache.get(name) {
+        return path.clone();
+    }
+
+    match path_from_cwd(workspace_folder, name).or_else(|| path_from_filesystem(name)) {
+        Some(path) => {
+            cache.insert(name.to_string(), path.clone());
+            save(cache);
+            path
+        }
+        None => decode_workspace_name(name),
+    }
+}